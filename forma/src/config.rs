@@ -0,0 +1,293 @@
+//! Configuration files
+//!
+//! Options may be kept alongside the SQL they apply to in a `forma.toml` file. The file nearest to
+//! the input, searching upwards from its directory, is used. A config file may name a `style`
+//! preset and override any of its individual options, e.g.
+//!
+//! ```toml
+//! style = "sqlstyle"
+//! max_width = 80
+//! ```
+//!
+//! Templates written in something other than Jinja may list their delimiters, e.g. for ERB:
+//!
+//! ```toml
+//! [[templates]]
+//! open = "<%="
+//! close = "%>"
+//! kind = "expression"
+//!
+//! [[templates]]
+//! open = "<%"
+//! close = "%>"
+//! kind = "statement"
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use formation::options::{
+    AliasStyle, CommaPosition, Dialect, FormatOptions, IndentStyle, KeywordCase, Layout, Style,
+    StyleEdition, TemplateDelimiters,
+};
+use serde::Deserialize;
+
+use crate::paths::{self, Selection};
+
+/// The name of the file configuration is read from.
+pub const CONFIG_FILE_NAME: &str = "forma.toml";
+
+/// Formatting configuration, each key of which is optional.
+///
+/// Unset keys fall back to those of the `style` preset, itself defaulting to `Style::Forma`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub style: Option<Style>,
+    pub max_width: Option<usize>,
+    pub indent_width: Option<usize>,
+    pub indent_style: Option<IndentStyle>,
+    pub comma_position: Option<CommaPosition>,
+    pub layout: Option<Layout>,
+    pub column_alias: Option<AliasStyle>,
+    pub table_alias: Option<AliasStyle>,
+    pub keyword_case: Option<KeywordCase>,
+    pub style_edition: Option<StyleEdition>,
+    pub dialect: Option<Dialect>,
+    pub templates: Option<Vec<TemplateDelimiters>>,
+    pub extensions: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Reads the config file at the given path.
+    pub fn load(path: &Path) -> Result<Config> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Searches `dir` and its ancestors for a config file, returning the nearest one's path.
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Overlays `other` on top of this config, such that keys set in `other` win.
+    pub fn merge(self, other: Config) -> Config {
+        Config {
+            style: other.style.or(self.style),
+            max_width: other.max_width.or(self.max_width),
+            indent_width: other.indent_width.or(self.indent_width),
+            indent_style: other.indent_style.or(self.indent_style),
+            comma_position: other.comma_position.or(self.comma_position),
+            layout: other.layout.or(self.layout),
+            column_alias: other.column_alias.or(self.column_alias),
+            table_alias: other.table_alias.or(self.table_alias),
+            keyword_case: other.keyword_case.or(self.keyword_case),
+            style_edition: other.style_edition.or(self.style_edition),
+            dialect: other.dialect.or(self.dialect),
+            templates: other.templates.or(self.templates),
+            extensions: other.extensions.or(self.extensions),
+            exclude: other.exclude.or(self.exclude),
+        }
+    }
+
+    /// Resolves the config into complete format options.
+    pub fn format_options(&self) -> FormatOptions {
+        let defaults = self.style.unwrap_or(Style::Forma).options();
+        FormatOptions {
+            max_width: self.max_width.unwrap_or(defaults.max_width),
+            indent_width: self.indent_width.unwrap_or(defaults.indent_width),
+            indent_style: self.indent_style.unwrap_or(defaults.indent_style),
+            comma_position: self.comma_position.unwrap_or(defaults.comma_position),
+            layout: self.layout.unwrap_or(defaults.layout),
+            column_alias: self.column_alias.unwrap_or(defaults.column_alias),
+            table_alias: self.table_alias.unwrap_or(defaults.table_alias),
+            keyword_case: self.keyword_case.unwrap_or(defaults.keyword_case),
+            style_edition: self.style_edition.unwrap_or(defaults.style_edition),
+            dialect: self.dialect.unwrap_or(defaults.dialect),
+            templates: self.templates.clone().unwrap_or(defaults.templates),
+        }
+    }
+
+    /// Resolves which files beneath the inputs are formatted, reading the `exclude` patterns
+    /// relative to the given directory, i.e. that of the config file. Fails if a pattern is
+    /// invalid.
+    pub fn selection(&self, base: &Path, force_exclude: bool) -> Result<Selection> {
+        Ok(Selection {
+            extensions: self
+                .extensions
+                .clone()
+                .unwrap_or_else(|| vec![paths::DEFAULT_EXTENSION.to_owned()]),
+            exclude: paths::rules(base, self.exclude.as_deref().unwrap_or_default())?,
+            force_exclude,
+        })
+    }
+}
+
+/// What a config resolves to for the files it applies to.
+pub struct Settings {
+    /// The options to format with.
+    pub options: FormatOptions,
+    /// Whether a dialect is given, rather than left to be detected.
+    pub dialect_configured: bool,
+    /// Which files beneath a directory are formatted.
+    pub selection: Selection,
+}
+
+/// Finds the config which applies to each directory, each merged with the command line flags.
+///
+/// Every file is formatted with the config nearest to it, unless one is given on the command line.
+/// Each config file is read once, however many files it applies to.
+pub struct Configs {
+    flags: Config,
+    config_file: Option<PathBuf>,
+    force_exclude: bool,
+    dirs: HashMap<PathBuf, Rc<Settings>>,
+    files: HashMap<Option<PathBuf>, Rc<Settings>>,
+}
+
+impl Configs {
+    /// Creates a cache of configs, overlaid with the given flags. Given a config file, it applies
+    /// to every directory.
+    pub fn new(flags: Config, config_file: Option<PathBuf>, force_exclude: bool) -> Configs {
+        Configs {
+            flags,
+            config_file,
+            force_exclude,
+            dirs: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Returns the settings for the files of the given absolute directory.
+    pub fn settings(&mut self, dir: &Path) -> Result<Rc<Settings>> {
+        if let Some(settings) = self.dirs.get(dir) {
+            return Ok(Rc::clone(settings));
+        }
+        let config_file = match &self.config_file {
+            Some(config_file) => Some(config_file.clone()),
+            None => Config::discover(dir),
+        };
+        let settings = match self.files.get(&config_file) {
+            Some(settings) => Rc::clone(settings),
+            None => {
+                let settings = Rc::new(self.load(config_file.as_deref())?);
+                self.files.insert(config_file, Rc::clone(&settings));
+                settings
+            }
+        };
+        self.dirs.insert(dir.to_path_buf(), Rc::clone(&settings));
+        Ok(settings)
+    }
+
+    /// Reads the given config file, if any, resolving its settings. Without a config file,
+    /// `exclude` patterns given as flags are relative to the working directory.
+    fn load(&self, config_file: Option<&Path>) -> Result<Settings> {
+        let (config, base) = match config_file {
+            Some(config_file) => (
+                Config::load(config_file)?,
+                fs::canonicalize(config_file)?
+                    .parent()
+                    .map(PathBuf::from)
+                    .unwrap_or_default(),
+            ),
+            None => (Config::default(), fs::canonicalize(env::current_dir()?)?),
+        };
+        let config = config.merge(self.flags.clone());
+        let options = config.format_options();
+        options.validate()?;
+        Ok(Settings {
+            options,
+            dialect_configured: config.dialect.is_some(),
+            selection: config.selection(&base, self.force_exclude)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formation::options::TemplateKind;
+
+    #[test]
+    fn test_config_from_toml() {
+        let config: Config = toml::from_str(
+            r#"
+            style = "enterprise"
+            max_width = 80
+            layout = "river"
+            style_edition = "0.4"
+            dialect = "postgresql"
+            extensions = ["sql", "tsql"]
+            exclude = ["target/"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                style: Some(Style::Enterprise),
+                max_width: Some(80),
+                layout: Some(Layout::River),
+                style_edition: Some(StyleEdition::V0_4),
+                dialect: Some(Dialect::PostgreSql),
+                extensions: Some(vec!["sql".to_owned(), "tsql".to_owned()]),
+                exclude: Some(vec!["target/".to_owned()]),
+                ..Config::default()
+            }
+        );
+        assert!(toml::from_str::<Config>("unknown_key = 1").is_err());
+
+        let config: Config = toml::from_str(
+            r#"
+            [[templates]]
+            open = "<%="
+            close = "%>"
+            kind = "expression"
+
+            [[templates]]
+            open = "<%#"
+            close = "%>"
+            kind = "comment"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.format_options().templates,
+            vec![
+                TemplateDelimiters::new("<%=", "%>", TemplateKind::Expression),
+                TemplateDelimiters::new("<%#", "%>", TemplateKind::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_options() {
+        assert_eq!(Config::default().format_options(), FormatOptions::default());
+        let config = Config {
+            style: Some(Style::Enterprise),
+            max_width: Some(80),
+            ..Config::default()
+        }
+        .merge(Config {
+            max_width: Some(120),
+            comma_position: Some(CommaPosition::Trailing),
+            ..Config::default()
+        });
+        assert_eq!(
+            config.format_options(),
+            FormatOptions {
+                max_width: 120,
+                comma_position: CommaPosition::Trailing,
+                ..Style::Enterprise.options()
+            }
+        );
+    }
+}
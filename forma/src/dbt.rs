@@ -0,0 +1,121 @@
+//! dbt projects
+//!
+//! Given `--dbt`, `forma` formats every model of a dbt project. The project is found by searching
+//! upwards for its `dbt_project.yml`, whose `model-paths` (`source-paths` before dbt 1.0) list the
+//! directories holding its models, e.g.
+//!
+//! ```yaml
+//! name: shop
+//! model-paths: ["models", "staging"]
+//! ```
+//!
+//! These are walked as any directory given on the command line is, skipping whatever ignore files
+//! or the config exclude.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::paths::{self, Selection};
+
+/// The name of the file describing a dbt project.
+pub const PROJECT_FILE_NAME: &str = "dbt_project.yml";
+
+/// The directory models are kept in unless the project says otherwise.
+const DEFAULT_MODEL_PATH: &str = "models";
+
+/// The keys of a project file which are of interest, the rest being ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct Project {
+    model_paths: Option<Vec<String>>,
+    source_paths: Option<Vec<String>>,
+}
+
+/// Searches `dir` and its ancestors for a dbt project file, returning the nearest one's path.
+pub fn discover(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Lists the files of every model directory of the project at the given path, in order.
+///
+/// The directories are walked as any other, skipping whatever the given selection excludes.
+pub fn models(project_file: &Path, selection: &Selection) -> Result<Vec<PathBuf>> {
+    let contents = fs::read_to_string(project_file)
+        .with_context(|| format!("Unable to read dbt project {}", project_file.display()))?;
+    let root = project_file.parent().unwrap_or_else(|| Path::new("."));
+    let dirs: Vec<PathBuf> = model_paths(&contents)
+        .with_context(|| format!("Invalid dbt project {}", project_file.display()))?
+        .into_iter()
+        .map(|model_path| root.join(model_path))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    paths::expand(&dirs, selection)
+}
+
+/// Reads the model paths from the contents of a project file.
+fn model_paths(contents: &str) -> Result<Vec<String>> {
+    let project: Project = serde_yaml::from_str(contents)?;
+    Ok(project
+        .model_paths
+        .or(project.source_paths)
+        .unwrap_or_else(|| vec![DEFAULT_MODEL_PATH.to_owned()]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_paths() -> Result<()> {
+        assert_eq!(model_paths("name: shop\n")?, vec!["models"]);
+        assert_eq!(
+            model_paths("name: shop\nmodel-paths: [\"models\", 'staging'] # both\n")?,
+            vec!["models", "staging"]
+        );
+        assert_eq!(
+            model_paths("source-paths:\n  - models\n\n  - \"marts\"\nversion: 2\n")?,
+            vec!["models", "marts"]
+        );
+        // Nested keys of the same name are not the project's.
+        assert_eq!(
+            model_paths("models:\n  shop:\n    model-paths: [\"other\"]\n")?,
+            vec!["models"]
+        );
+        assert!(model_paths("model-paths: models\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_models() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join(PROJECT_FILE_NAME),
+            "model-paths: [\"models\", \"missing\"]\n",
+        )?;
+        fs::create_dir_all(dir.path().join("models/staging"))?;
+        fs::write(dir.path().join("models/b.sql"), "select 1")?;
+        fs::write(dir.path().join("models/staging/a.sql"), "select 1")?;
+        fs::write(dir.path().join("models/schema.yml"), "version: 2")?;
+        fs::create_dir(dir.path().join("models/legacy"))?;
+        fs::write(dir.path().join("models/legacy/c.sql"), "select 1")?;
+        fs::write(dir.path().join("models/.formaignore"), "legacy/\n")?;
+        let project_file = discover(&dir.path().join("models/staging")).unwrap();
+        let selection = Selection {
+            extensions: vec![paths::DEFAULT_EXTENSION.to_owned()],
+            ..Selection::default()
+        };
+        assert_eq!(
+            models(&project_file, &selection)?,
+            vec![
+                dir.path().join("models/b.sql"),
+                dir.path().join("models/staging/a.sql")
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,25 @@
 //! An opinionated SQL formatter
 //!
-//! This provides a command line utility for formatting SQL. Input may be provided as either a file
-//! path or stdin. In the case of the former, the original file will be re-formatted unless the
-//! `--check` flag is provided. (The `--check` flag will return a non-zero error code if `forma`
-//! would reformat the given input.) Input from stdin will be output to stdout.
+//! This provides a command line utility for formatting SQL. Input may be provided as either any
+//! number of paths or stdin. Each path may be a file, a directory, walked for files with one of the
+//! configured extensions, or a glob. Files will be re-formatted unless the `--check` flag is
+//! provided, with the result for each reported to stderr. (The `--check` flag will return a
+//! non-zero error code if `forma` would reformat any of the given input.) Input from stdin will be
+//! output to stdout, with `--stdin-filepath` naming the file it belongs to for the sake of config
+//! and dialect detection. `--stdout` and `--output` write formatted files elsewhere instead.
+//! Walking skips whatever `.gitignore` and `.formaignore` files or the config's `exclude` patterns
+//! exclude.
+//!
+//! Options are taken from the nearest `forma.toml`, if any, with command line flags taking
+//! precedence. Either may select a named `style` preset as the starting point. Where neither
+//! names a dialect, it is detected from the input's path and contents under style edition 0.4, and
+//! is `templated` under 0.3.
+//!
+//! Given `--diff`, the changes formatting would make are printed as a unified diff rather than
+//! applied. Together with `--check`, which then fails if there are any, this suits CI.
+//!
+//! Given `--dbt`, every model of the dbt project containing the input, or the working directory,
+//! is formatted in place.
 //!
 //! The companion library [`formation`], is generalized to be usable in other contexts, such as
 //! your own programs.
@@ -11,44 +27,251 @@
 //! [`formation`]: ../formation/index.html
 
 #![deny(clippy::all, missing_docs)]
-#![feature(with_options)]
 
+mod config;
+mod dbt;
+mod paths;
+
+use std::env;
 use std::fs;
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::slice;
 
-use anyhow::Result;
-use formation::format;
+use anyhow::{anyhow, Result};
+use formation::{
+    diff::unified_diff,
+    error::FormaError,
+    format_detected, format_with_options,
+    options::{
+        AliasStyle, CommaPosition, Dialect, FormatOptions, IndentStyle, KeywordCase, Layout, Style,
+        StyleEdition,
+    },
+};
+use log::{Level, LevelFilter, Log, Metadata, Record};
 use structopt::StructOpt;
+use tempfile::NamedTempFile;
 
-const DEFAULT_MAX_WIDTH: &str = "100";
+use crate::config::{Config, Configs, Settings};
+use crate::paths::Selection;
 
-#[derive(Debug, PartialEq, StructOpt)]
+#[derive(Debug, Default, PartialEq, StructOpt)]
 #[structopt(name = "forma", about = "🐚 An opinionated SQL formatter.")]
 struct Opt {
-    /// A SQL input to format; either a file path or stdin.
+    /// The SQL inputs to format; files, directories or globs, else stdin.
     #[structopt(parse(from_os_str))]
-    input: Option<PathBuf>,
+    inputs: Vec<PathBuf>,
 
     /// Check if formatting would occur without applying it.
     #[structopt(long)]
     check: bool,
 
-    /// The maximum allowed column width before wrapping.
-    #[structopt(long = "max-width", default_value = DEFAULT_MAX_WIDTH)]
-    max_width: usize,
+    /// Print a diff of the changes formatting would make instead of applying them; with `--check`,
+    /// fail if there are any.
+    #[structopt(long)]
+    diff: bool,
+
+    /// Report decisions made along the way, such as a detected dialect, to stderr.
+    #[structopt(short, long)]
+    verbose: bool,
+
+    /// Format every model of the dbt project containing the input, or the working directory, as
+    /// found in the model paths of its `dbt_project.yml`.
+    #[structopt(long)]
+    dbt: bool,
+
+    /// The extensions of files to format when walking a directory [default: sql].
+    #[structopt(long, use_delimiter = true)]
+    extensions: Vec<String>,
+
+    /// The path of the SQL given via stdin, used to find its config and detect its dialect.
+    #[structopt(long = "stdin-filepath", parse(from_os_str), conflicts_with = "inputs")]
+    stdin_filepath: Option<PathBuf>,
+
+    /// Print formatted files to stdout instead of overwriting them.
+    #[structopt(long)]
+    stdout: bool,
+
+    /// Write the formatted SQL of stdin, or of a single file, to the given file instead.
+    #[structopt(long, parse(from_os_str), conflicts_with = "stdout")]
+    output: Option<PathBuf>,
+
+    /// Skip files given by name which an ignore file or the config excludes, as when walking.
+    #[structopt(long = "force-exclude")]
+    force_exclude: bool,
+
+    /// A config file to use instead of the nearest `forma.toml`.
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// A named preset of options, which the options below override [default: forma].
+    #[structopt(long, possible_values = &["forma", "sqlstyle", "enterprise"])]
+    style: Option<Style>,
+
+    /// The maximum allowed column width before wrapping [default: 100].
+    #[structopt(long = "max-width")]
+    max_width: Option<usize>,
+
+    /// The number of columns a single level of indentation occupies [default: 2].
+    #[structopt(long = "indent-width")]
+    indent_width: Option<usize>,
+
+    /// Indent with tabs instead of spaces; a tab counts as `--indent-width` columns.
+    #[structopt(long)]
+    tabs: bool,
+
+    /// Where commas are placed when a comma-separated list breaks across lines [default: trailing].
+    #[structopt(long = "comma-position", possible_values = &["trailing", "leading"])]
+    comma_position: Option<CommaPosition>,
+
+    /// How clause keywords are arranged; `river` right-aligns them so clause bodies line up
+    /// [default: standard].
+    #[structopt(long, possible_values = &["standard", "river"])]
+    layout: Option<Layout>,
+
+    /// Whether column aliases are written with `as` [default: explicit].
+    #[structopt(long = "column-alias", possible_values = &["explicit", "implicit", "preserve"])]
+    column_alias: Option<AliasStyle>,
+
+    /// Whether table aliases are written with `as` [default: explicit].
+    #[structopt(long = "table-alias", possible_values = &["explicit", "implicit", "preserve"])]
+    table_alias: Option<AliasStyle>,
+
+    /// The case keywords are written in [default: lower].
+    #[structopt(long = "keyword-case", possible_values = &["lower", "upper"])]
+    keyword_case: Option<KeywordCase>,
+
+    /// The edition of layout behaviors to adhere to; pin it to avoid churn on upgrades
+    /// [default: 0.3].
+    #[structopt(long = "style-edition", possible_values = &["0.3", "0.4"])]
+    style_edition: Option<StyleEdition>,
+
+    /// The SQL dialect to parse input as [default: detected from the input under style edition
+    /// 0.4, otherwise templated].
+    #[structopt(
+        long,
+        possible_values = &[
+            "templated",
+            "generic",
+            "ansi",
+            "postgresql",
+            "mysql",
+            "mssql",
+            "sqlite",
+            "bigquery",
+            "snowflake",
+        ]
+    )]
+    dialect: Option<Dialect>,
+}
+
+impl Opt {
+    /// Returns the options given as flags, which take precedence over those of any config file.
+    fn config(&self) -> Config {
+        Config {
+            style: self.style,
+            max_width: self.max_width,
+            indent_width: self.indent_width,
+            indent_style: if self.tabs {
+                Some(IndentStyle::Tabs)
+            } else {
+                None
+            },
+            comma_position: self.comma_position,
+            layout: self.layout,
+            column_alias: self.column_alias,
+            table_alias: self.table_alias,
+            keyword_case: self.keyword_case,
+            style_edition: self.style_edition,
+            dialect: self.dialect,
+            templates: None,
+            extensions: if self.extensions.is_empty() {
+                None
+            } else {
+                Some(self.extensions.clone())
+            },
+            exclude: None,
+        }
+    }
+}
+
+/// Writes warnings from `formation`, e.g. about SQL left as written, to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("warning: {}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// How the dialect of the input is settled.
+#[derive(Clone, Copy)]
+enum DialectChoice {
+    /// The dialect of the format options is used as is.
+    Configured,
+    /// The dialect is detected, taking hints from the input's path, if any.
+    Detected { verbose: bool },
 }
 
-/// Given a reader, a writer, a check bool, and max width to format to, formats the reader's string
-/// value and then writes the result via the writer.
-fn formatter<R, W>(mut reader: R, mut writer: W, check: bool, max_width: usize) -> Result<()>
+impl DialectChoice {
+    /// Settles the dialect as the given settings say, detecting it unless they name one. Detection
+    /// is only done from edition 0.4, as it changes the output of SQL without a configured dialect.
+    fn new(settings: &Settings, verbose: bool) -> DialectChoice {
+        if settings.dialect_configured || settings.options.style_edition < StyleEdition::V0_4 {
+            DialectChoice::Configured
+        } else {
+            DialectChoice::Detected { verbose }
+        }
+    }
+}
+
+/// Where formatted SQL is written.
+#[derive(Clone, Copy)]
+enum Output<'a> {
+    /// Over the file it was read from, or to stdout if read from stdin.
+    InPlace,
+    /// To stdout.
+    Stdout,
+    /// To the given file.
+    File(&'a Path),
+}
+
+/// Given a reader, a writer, the path the reader's SQL belongs to, if any, the options to format
+/// with and how to settle the dialect, formats the reader's string value and then writes the
+/// result via the writer.
+fn formatter<R, W>(
+    mut reader: R,
+    mut writer: W,
+    path: Option<&Path>,
+    options: &FormatOptions,
+    dialect: DialectChoice,
+) -> Result<()>
 where
     W: Write,
     R: BufRead,
 {
     let mut sql = String::new();
     reader.read_to_string(&mut sql)?;
-    let formatted = format(&sql, check, max_width)?;
+    let formatted = match dialect {
+        DialectChoice::Configured => format_with_options(&sql, false, options)?,
+        DialectChoice::Detected { verbose } => {
+            let (dialect, formatted) = format_detected(&sql, path, false, options)?;
+            if verbose {
+                eprintln!("Detected dialect `{}`", dialect);
+            }
+            formatted
+        }
+    };
     writer.write_all(
         &formatted
             .iter()
@@ -58,22 +281,312 @@ where
     Ok(())
 }
 
+/// Formats the file at the given path, returning whether its contents changed.
+///
+/// A file whose contents would not change is left untouched, keeping its modification time. Given
+/// `diff`, the changes are printed rather than applied. Given an output file, whether that file
+/// changed is returned instead.
+fn format_file(
+    path: &Path,
+    check: bool,
+    diff: bool,
+    output: Output,
+    options: &FormatOptions,
+    dialect: DialectChoice,
+) -> Result<bool> {
+    let original = fs::read(path)?;
+    let mut formatted = Vec::new();
+    formatter(&original[..], &mut formatted, Some(path), options, dialect)?;
+    if diff {
+        return print_diff(&original, &formatted, &path.display().to_string(), check);
+    }
+    // The file as a whole is compared, as formatting may also change what lies between
+    // statements.
+    if check && formatted != original {
+        return Err(FormaError::WouldFormat.into());
+    } else if check {
+        return Ok(false);
+    }
+    match output {
+        Output::InPlace if formatted != original => write_atomically(path, &formatted)?,
+        Output::InPlace => {}
+        Output::Stdout => io::stdout().write_all(&formatted)?,
+        Output::File(output) => return write_output(output, &formatted),
+    }
+    Ok(formatted != original)
+}
+
+/// Writes formatted SQL to the given file, unless it already holds it, returning whether it was
+/// written.
+fn write_output(path: &Path, contents: &[u8]) -> Result<bool> {
+    if fs::read(path).ok().as_deref() == Some(contents) {
+        return Ok(false);
+    }
+    write_atomically(path, contents)?;
+    Ok(true)
+}
+
+/// Prints a diff of the changes formatting makes to stdout, colored where stdout is a terminal,
+/// returning whether there are any. Given `check`, any change is an error.
+fn print_diff(original: &[u8], formatted: &[u8], path: &str, check: bool) -> Result<bool> {
+    let original = String::from_utf8_lossy(original);
+    let formatted = String::from_utf8_lossy(formatted);
+    let diff = match unified_diff(&original, &formatted, path) {
+        Some(diff) => diff,
+        None => return Ok(false),
+    };
+    let diff = if atty::is(atty::Stream::Stdout) {
+        colorize(&diff)
+    } else {
+        diff
+    };
+    io::stdout().write_all(diff.as_bytes())?;
+    if check {
+        Err(FormaError::WouldFormat.into())
+    } else {
+        Ok(true)
+    }
+}
+
+/// Colors the lines of a unified diff in the manner of `git diff`.
+fn colorize(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let color = if line.starts_with("---") || line.starts_with("+++") {
+                "1"
+            } else if line.starts_with("@@") {
+                "36"
+            } else if line.starts_with('-') {
+                "31"
+            } else if line.starts_with('+') {
+                "32"
+            } else {
+                return format!("{}\n", line);
+            };
+            format!("\x1b[{}m{}\x1b[0m\n", color, line)
+        })
+        .collect()
+}
+
+/// Replaces the contents of the file at the given path, keeping its permissions, or creates it.
+///
+/// The contents are written to a temporary file beside it which is then renamed over it, such that
+/// the file is never left partly written. Symlinks are followed, leaving the link itself in place.
+/// A new file is created empty first, so that it takes the usual permissions for the umask rather
+/// than those of a temporary file.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let permissions = match fs::metadata(&path) {
+        Ok(metadata) => metadata.permissions(),
+        Err(_) => fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?
+            .metadata()?
+            .permissions(),
+    };
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+    fs::set_permissions(file.path(), permissions)?;
+    file.persist(&path)?;
+    Ok(())
+}
+
+/// Returns the absolute directory whose config applies to the given path: the path itself, for a
+/// directory, else the directory holding it.
+///
+/// The path need not exist, e.g. a glob or an unsaved editor buffer given via `--stdin-filepath`.
+fn config_dir(path: &Path) -> Result<PathBuf> {
+    if path.is_dir() {
+        return Ok(fs::canonicalize(path)?);
+    }
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => fs::canonicalize(env::current_dir()?)?.join(path),
+    };
+    Ok(path.parent().map(PathBuf::from).unwrap_or_default())
+}
+
+/// Formats each of the given files with the settings of the config nearest to it, reporting the
+/// result of each to stderr.
+///
+/// Every file is attempted, even once one has failed.
+fn format_files(
+    paths: &[PathBuf],
+    check: bool,
+    diff: bool,
+    output: Output,
+    configs: &mut Configs,
+    verbose: bool,
+) -> Result<()> {
+    if let (Output::File(_), true) = (output, paths.len() > 1) {
+        return Err(anyhow!(
+            "--output may only be given a single file to format"
+        ));
+    }
+    let mut failures = 0;
+    for path in paths {
+        // Writing goes to the output file, if given, so that's the one reported.
+        let destination = match output {
+            Output::File(output) if !check && !diff => output,
+            _ => path.as_path(),
+        };
+        let formatted = config_dir(path)
+            .and_then(|dir| configs.settings(&dir))
+            .and_then(|settings| {
+                let dialect = DialectChoice::new(&settings, verbose);
+                format_file(path, check, diff, output, &settings.options, dialect)
+            });
+        match formatted {
+            Ok(_) if check => eprintln!("Checked {}", path.display()),
+            Ok(true) if diff => eprintln!("Would format {}", path.display()),
+            Ok(true) => eprintln!("Formatted {}", destination.display()),
+            Ok(false) => eprintln!("Unchanged {}", destination.display()),
+            Err(error) => {
+                failures += 1;
+                match error.downcast_ref() {
+                    Some(FormaError::WouldFormat) => eprintln!("Would format {}", path.display()),
+                    _ => eprintln!("Unable to format {}: {:#}", path.display(), error),
+                }
+            }
+        }
+    }
+    match failures {
+        0 => Ok(()),
+        _ if check => Err(anyhow!(
+            "Check failed; {} of {} files would be formatted or could not be",
+            failures,
+            paths.len()
+        )),
+        _ => Err(anyhow!(
+            "{} of {} files could not be formatted",
+            failures,
+            paths.len()
+        )),
+    }
+}
+
+/// Formats SQL from stdin, writing it to stdout unless given another output.
+///
+/// The path the SQL belongs to, if given, is used to detect its dialect and label its diff.
+/// Should the path be excluded and exclusion forced, the SQL is written as it was read.
+fn format_stdin(
+    path: Option<&Path>,
+    check: bool,
+    diff: bool,
+    output: Output,
+    options: &FormatOptions,
+    dialect: DialectChoice,
+    selection: &Selection,
+) -> Result<()> {
+    let mut sql = Vec::new();
+    io::stdin().read_to_end(&mut sql)?;
+    let excluded = match path {
+        Some(path) if selection.force_exclude && path.exists() => {
+            selection.is_path_excluded(path)?
+        }
+        _ => false,
+    };
+    let formatted = if excluded {
+        sql.clone()
+    } else {
+        let mut formatted = Vec::new();
+        formatter(&sql[..], &mut formatted, path, options, dialect)?;
+        formatted
+    };
+    if diff {
+        let label = path.map_or_else(|| "<stdin>".to_owned(), |path| path.display().to_string());
+        print_diff(&sql, &formatted, &label, check)?;
+        return Ok(());
+    }
+    if check && formatted != sql {
+        return Err(FormaError::WouldFormat.into());
+    }
+    match output {
+        Output::File(_) if check => Ok(()),
+        Output::File(output) => write_output(output, &formatted).map(|_| ()),
+        Output::InPlace | Output::Stdout => Ok(io::stdout().write_all(&formatted)?),
+    }
+}
+
 /// Main entrypoint for the `forma` binary.
 fn main() -> Result<()> {
-    let Opt {
-        input,
-        check,
-        max_width,
-    } = Opt::from_args();
-    match input {
-        Some(input) => formatter(
-            BufReader::new(fs::File::open(&input)?),
-            fs::File::with_options().write(true).open(input)?,
-            check,
-            max_width,
-        ),
-        None => formatter(io::stdin().lock(), io::stdout(), check, max_width),
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Warn);
     }
+    let opt = Opt::from_args();
+    let dbt_project = if opt.dbt {
+        let search_dir = match opt.inputs.first() {
+            Some(input) => fs::canonicalize(input)?,
+            None => env::current_dir()?,
+        };
+        Some(dbt::discover(&search_dir).ok_or_else(|| {
+            anyhow!(
+                "No {} found in {} or its ancestors",
+                dbt::PROJECT_FILE_NAME,
+                search_dir.display()
+            )
+        })?)
+    } else {
+        None
+    };
+    let output = match (&opt.output, opt.stdout) {
+        (Some(output), _) => Output::File(output),
+        (None, true) => Output::Stdout,
+        (None, false) => Output::InPlace,
+    };
+    let mut configs = Configs::new(opt.config(), opt.config.clone(), opt.force_exclude);
+    if let Some(project_file) = dbt_project {
+        let project_dir = config_dir(&project_file)?;
+        let models = dbt::models(&project_file, &configs.settings(&project_dir)?.selection)?;
+        return format_files(
+            &models,
+            opt.check,
+            opt.diff,
+            output,
+            &mut configs,
+            opt.verbose,
+        );
+    }
+    if opt.inputs.is_empty() {
+        let dir = match &opt.stdin_filepath {
+            Some(path) => config_dir(path)?,
+            None => fs::canonicalize(env::current_dir()?)?,
+        };
+        let settings = configs.settings(&dir)?;
+        return format_stdin(
+            opt.stdin_filepath.as_deref(),
+            opt.check,
+            opt.diff,
+            output,
+            &settings.options,
+            DialectChoice::new(&settings, opt.verbose),
+            &settings.selection,
+        );
+    }
+    // Each input is expanded as the config nearest to it says.
+    let mut paths: Vec<PathBuf> = vec![];
+    for input in &opt.inputs {
+        let settings = configs.settings(&config_dir(input)?)?;
+        for path in paths::expand(slice::from_ref(input), &settings.selection)? {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    format_files(
+        &paths,
+        opt.check,
+        opt.diff,
+        output,
+        &mut configs,
+        opt.verbose,
+    )
 }
 
 #[cfg(test)]
@@ -86,28 +599,271 @@ mod tests {
     fn test_formatter() -> Result<()> {
         let input = b"SELECT * FROM t1";
         let mut output = Vec::new();
-        formatter(&input[..], &mut output, false, 100)?;
+        formatter(
+            &input[..],
+            &mut output,
+            None,
+            &FormatOptions::default(),
+            DialectChoice::Configured,
+        )?;
         let output = String::from_utf8(output)?;
         assert_eq!(output, "select * from t1;\n");
         Ok(())
     }
 
+    #[test]
+    fn test_formatter_tabs() -> Result<()> {
+        let input = b"SELECT id, name FROM t1";
+        let mut output = Vec::new();
+        let options = FormatOptions {
+            max_width: 10,
+            indent_width: 4,
+            indent_style: IndentStyle::Tabs,
+            ..FormatOptions::default()
+        };
+        formatter(
+            &input[..],
+            &mut output,
+            None,
+            &options,
+            DialectChoice::Configured,
+        )?;
+        let output = String::from_utf8(output)?;
+        assert_eq!(output, "select\n\tid,\n\tname\nfrom\n\tt1;\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_formatter_detected() -> Result<()> {
+        let input = b"SELECT [Id] FROM [dbo].[Users]\nGO\n";
+        let mut output = Vec::new();
+        formatter(
+            &input[..],
+            &mut output,
+            Some(Path::new("users.tsql")),
+            &FormatOptions {
+                style_edition: StyleEdition::V0_4,
+                ..FormatOptions::default()
+            },
+            DialectChoice::Detected { verbose: false },
+        )?;
+        let output = String::from_utf8(output)?;
+        assert_eq!(output, "select [Id] from [dbo].[Users];\nGO\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("example.sql");
+        fs::write(&path, "SELECT    *    FROM    t1")?;
+        let options = FormatOptions::default();
+        assert!(format_file(
+            &path,
+            true,
+            false,
+            Output::InPlace,
+            &options,
+            DialectChoice::Configured
+        )
+        .is_err());
+        assert_eq!(fs::read_to_string(&path)?, "SELECT    *    FROM    t1");
+        assert!(format_file(
+            &path,
+            false,
+            false,
+            Output::InPlace,
+            &options,
+            DialectChoice::Configured
+        )?);
+        // Shorter output replaces the original entirely.
+        assert_eq!(fs::read_to_string(&path)?, "select * from t1;\n");
+        let modified = fs::metadata(&path)?.modified()?;
+        assert!(!format_file(
+            &path,
+            false,
+            false,
+            Output::InPlace,
+            &options,
+            DialectChoice::Configured
+        )?);
+        assert_eq!(fs::metadata(&path)?.modified()?, modified);
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_file_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("example.sql");
+        fs::write(&path, "SELECT * FROM t1")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640))?;
+        let options = FormatOptions::default();
+        assert!(format_file(
+            &path,
+            false,
+            false,
+            Output::InPlace,
+            &options,
+            DialectChoice::Configured
+        )?);
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o640);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_file_diff() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("example.sql");
+        fs::write(&path, "SELECT * FROM t1")?;
+        let options = FormatOptions::default();
+        assert!(format_file(
+            &path,
+            false,
+            true,
+            Output::InPlace,
+            &options,
+            DialectChoice::Configured
+        )?);
+        assert!(format_file(
+            &path,
+            true,
+            true,
+            Output::InPlace,
+            &options,
+            DialectChoice::Configured
+        )
+        .is_err());
+        assert_eq!(fs::read_to_string(&path)?, "SELECT * FROM t1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_colorize() {
+        assert_eq!(
+            colorize("--- a.sql (original)\n+++ a.sql (formatted)\n@@ -1 +1 @@\n-A\n+a\n b\n"),
+            concat!(
+                "\x1b[1m--- a.sql (original)\x1b[0m\n",
+                "\x1b[1m+++ a.sql (formatted)\x1b[0m\n",
+                "\x1b[36m@@ -1 +1 @@\x1b[0m\n",
+                "\x1b[31m-A\x1b[0m\n",
+                "\x1b[32m+a\x1b[0m\n",
+                " b\n",
+            )
+        );
+    }
+
     #[test]
     fn test_opt() {
+        assert_eq!(Opt::from_iter(&["test"]), Opt::default());
         assert_eq!(
-            Opt::from_iter(&["test"]),
+            Opt::from_iter(&["test", "some/sql/path.sql"]),
             Opt {
-                input: None,
-                check: false,
-                max_width: DEFAULT_MAX_WIDTH.parse::<usize>().unwrap()
+                inputs: vec![PathBuf::from_str("some/sql/path.sql").unwrap()],
+                ..Opt::default()
             }
         );
         assert_eq!(
-            Opt::from_iter(&["test", "some/sql/path.sql"]),
+            Opt::from_iter(&["test", "models", "a.sql", "--extensions", "sql,tsql"]),
             Opt {
-                input: Some(PathBuf::from_str("some/sql/path.sql").unwrap()),
-                check: false,
-                max_width: DEFAULT_MAX_WIDTH.parse::<usize>().unwrap()
+                inputs: vec![PathBuf::from("models"), PathBuf::from("a.sql")],
+                extensions: vec!["sql".to_owned(), "tsql".to_owned()],
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&["test", "--indent-width", "4", "--tabs"]),
+            Opt {
+                indent_width: Some(4),
+                tabs: true,
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&["test", "--comma-position", "leading"]),
+            Opt {
+                comma_position: Some(CommaPosition::Leading),
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&["test", "--layout", "river"]),
+            Opt {
+                layout: Some(Layout::River),
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&[
+                "test",
+                "--column-alias",
+                "implicit",
+                "--table-alias",
+                "preserve"
+            ]),
+            Opt {
+                column_alias: Some(AliasStyle::Implicit),
+                table_alias: Some(AliasStyle::Preserve),
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&["test", "--style", "enterprise", "--keyword-case", "lower"]),
+            Opt {
+                style: Some(Style::Enterprise),
+                keyword_case: Some(KeywordCase::Lower),
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&["test", "--style-edition", "0.4"]),
+            Opt {
+                style_edition: Some(StyleEdition::V0_4),
+                ..Opt::default()
+            }
+        );
+        assert_eq!(
+            Opt::from_iter(&["test", "--dialect", "postgresql", "-v"]),
+            Opt {
+                dialect: Some(Dialect::PostgreSql),
+                verbose: true,
+                ..Opt::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_opt_outputs() {
+        assert_eq!(
+            Opt::from_iter(&[
+                "test",
+                "--stdin-filepath",
+                "models/a.sql",
+                "--output",
+                "b.sql"
+            ]),
+            Opt {
+                stdin_filepath: Some(PathBuf::from("models/a.sql")),
+                output: Some(PathBuf::from("b.sql")),
+                ..Opt::default()
+            }
+        );
+        assert!(Opt::from_iter_safe(&["test", "a.sql", "--stdin-filepath", "b.sql"]).is_err());
+        assert!(Opt::from_iter_safe(&["test", "a.sql", "--stdout", "--output", "b.sql"]).is_err());
+    }
+
+    #[test]
+    fn test_opt_config() {
+        let opt = Opt::from_iter(&["test", "--style", "sqlstyle", "--max-width", "80", "--tabs"]);
+        assert_eq!(
+            opt.config().format_options(),
+            FormatOptions {
+                max_width: 80,
+                indent_style: IndentStyle::Tabs,
+                ..Style::Sqlstyle.options()
             }
         );
     }
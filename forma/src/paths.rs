@@ -0,0 +1,352 @@
+//! Input paths
+//!
+//! `forma` may be given any number of inputs, each a file, a directory or a glob. Directories are
+//! walked for files with one of the configured extensions, `.sql` by default, while globs are
+//! expanded against the files beneath their leading directory, e.g.
+//!
+//! ```text
+//! forma models/ 'migrations/**/*.sql' schema.sql
+//! ```
+//!
+//! Globs understand `*`, `?` and `[...]` within a path component, `{a,b}` alternatives and `**`
+//! across any number of components. Shells usually expand globs themselves; quoting one leaves it
+//! to `forma`.
+//!
+//! Walking skips whatever the `.gitignore` and `.formaignore` files of a directory, and of its
+//! ancestors within the same Git repository, exclude, along with the `exclude` patterns of the
+//! config, e.g.
+//!
+//! ```toml
+//! exclude = ["target/", "dumps/**/*.sql", "*_generated.sql"]
+//! ```
+//!
+//! Patterns are written as in a `.gitignore`. Files given by name are formatted regardless, unless
+//! `--force-exclude` is given.
+
+use std::fs;
+use std::iter;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use globset::GlobBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// The extension of files found when walking a directory unless configured otherwise.
+pub const DEFAULT_EXTENSION: &str = "sql";
+
+/// The names of the files listing the patterns a directory excludes.
+const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".formaignore"];
+
+/// Builds the rules of the given patterns, written as in a `.gitignore` relative to the given
+/// directory.
+pub fn rules(base: &Path, patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(base);
+    for pattern in patterns {
+        builder.add_line(None, pattern)?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Which of the files beneath the inputs are formatted.
+#[derive(Debug)]
+pub struct Selection {
+    /// The extensions of files found when walking a directory.
+    pub extensions: Vec<String>,
+    /// The patterns of the config, applied after those of any ignore file.
+    pub exclude: Gitignore,
+    /// Whether files given by name are excluded as if they had been walked.
+    pub force_exclude: bool,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection {
+            extensions: vec![],
+            exclude: Gitignore::empty(),
+            force_exclude: false,
+        }
+    }
+}
+
+impl Selection {
+    /// Returns `true` if the given absolute path is excluded by the given rules or the config.
+    fn is_excluded(&self, rules: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+        let matched = rules
+            .iter()
+            .chain(iter::once(&self.exclude))
+            .rev()
+            .filter(|rules| path.starts_with(rules.path()))
+            .map(|rules| rules.matched(path, is_dir))
+            .find(|matched| !matched.is_none());
+        matches!(matched, Some(matched) if matched.is_ignore())
+    }
+
+    /// Returns `true` if the given path, or any directory it is beneath, is excluded.
+    pub fn is_path_excluded(&self, path: &Path) -> Result<bool> {
+        let path = fs::canonicalize(path)?;
+        let rules = ancestor_rules(&path)?;
+        Ok(path.ancestors().enumerate().any(|(depth, ancestor)| {
+            self.is_excluded(&rules, ancestor, depth > 0 || path.is_dir())
+        }))
+    }
+}
+
+/// Expands the given inputs into the files they name, in order and without duplicates.
+///
+/// Files are kept whatever their extension, as they were asked for by name. A directory or a glob
+/// which selects no files is an error.
+pub fn expand(inputs: &[PathBuf], selection: &Selection) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for input in inputs {
+        let expanded =
+            if selection.force_exclude && input.exists() && selection.is_path_excluded(input)? {
+                vec![]
+            } else if input.is_dir() {
+                let walked: Vec<PathBuf> = walk(input, selection)?
+                    .into_iter()
+                    .filter(|path| {
+                        let extension = path.extension().and_then(|e| e.to_str());
+                        selection
+                            .extensions
+                            .iter()
+                            .any(|e| Some(e.as_str()) == extension)
+                    })
+                    .collect();
+                if walked.is_empty() {
+                    return Err(anyhow!(
+                        "No files with the extensions {} in {}",
+                        selection.extensions.join(", "),
+                        input.display()
+                    ));
+                }
+                walked
+            } else if !input.exists() && is_glob(input) {
+                let matched = glob(input, selection)?;
+                if matched.is_empty() {
+                    return Err(anyhow!("No files match {}", input.display()));
+                }
+                matched
+            } else {
+                vec![input.clone()]
+            };
+        for path in expanded {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Lists the files beneath the given directory which are not excluded, in order.
+fn walk(dir: &Path, selection: &Selection) -> Result<Vec<PathBuf>> {
+    let absolute = fs::canonicalize(dir)?;
+    let mut rules = ancestor_rules(&absolute)?;
+    let mut paths = vec![];
+    walk_dir(dir, &absolute, selection, &mut rules, &mut paths)?;
+    Ok(paths)
+}
+
+/// Walks the given directory, whose absolute path is also given, beneath the given rules.
+fn walk_dir(
+    dir: &Path,
+    absolute: &Path,
+    selection: &Selection,
+    rules: &mut Vec<Gitignore>,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let depth = rules.len();
+    rules.extend(load_rules(absolute)?);
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let entry_absolute = absolute.join(entry.file_name());
+        let is_dir = path.is_dir();
+        if entry.file_name() == ".git" || selection.is_excluded(rules, &entry_absolute, is_dir) {
+            continue;
+        }
+        if is_dir {
+            walk_dir(&path, &entry_absolute, selection, rules, paths)?;
+        } else if path.is_file() {
+            paths.push(path);
+        }
+    }
+    rules.truncate(depth);
+    Ok(())
+}
+
+/// Reads the rules of the ignore files of the ancestors of the given path, outermost first.
+///
+/// Only the ancestors within the path's Git repository are read, so that the ignore files of
+/// unrelated directories above it, e.g. a home directory, do not apply. Outside of a repository,
+/// only the directory holding a file is read.
+fn ancestor_rules(path: &Path) -> Result<Vec<Gitignore>> {
+    let ancestors: Vec<&Path> = path.ancestors().collect();
+    let root = ancestors
+        .iter()
+        .position(|ancestor| ancestor.join(".git").exists())
+        .unwrap_or_else(|| if path.is_file() { 1 } else { 0 });
+    let mut rules = vec![];
+    for ancestor in ancestors[1..=root].iter().rev() {
+        rules.extend(load_rules(ancestor)?);
+    }
+    Ok(rules)
+}
+
+/// Reads the rules of the ignore files of the given directory, if it has any.
+fn load_rules(dir: &Path) -> Result<Option<Gitignore>> {
+    let mut builder = GitignoreBuilder::new(dir);
+    for name in IGNORE_FILE_NAMES {
+        let path = dir.join(name);
+        if path.is_file() {
+            if let Some(error) = builder.add(path) {
+                return Err(error.into());
+            }
+        }
+    }
+    let rules = builder.build()?;
+    Ok(if rules.is_empty() { None } else { Some(rules) })
+}
+
+/// Returns `true` if the given path holds any glob syntax.
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(&['*', '?', '[', '{'][..])
+}
+
+/// Lists the files matching the given glob which are not excluded, in order.
+fn glob(pattern: &Path, selection: &Selection) -> Result<Vec<PathBuf>> {
+    // Only the components from the first with glob syntax on need matching; those before it name
+    // the directory to walk.
+    let all: Vec<Component<'_>> = pattern.components().collect();
+    let literal = all
+        .iter()
+        .position(|component| is_glob(component.as_os_str().as_ref()))
+        .unwrap_or(all.len());
+    let base: PathBuf = all[..literal].iter().collect();
+    let base = if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    };
+    let relative_pattern: PathBuf = all[literal..].iter().collect();
+    let matcher = GlobBuilder::new(&relative_pattern.to_string_lossy())
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+    let mut paths = vec![];
+    for path in walk(&base, selection)? {
+        let relative = path.strip_prefix(&base)?;
+        if matcher.is_match(relative) {
+            paths.push(if literal == 0 {
+                relative.to_path_buf()
+            } else {
+                path
+            });
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use super::*;
+
+    fn selection(extensions: &[&str]) -> Selection {
+        Selection {
+            extensions: extensions.iter().map(|e| (*e).to_owned()).collect(),
+            ..Selection::default()
+        }
+    }
+
+    #[test]
+    fn test_expand() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("models/staging"))?;
+        fs::write(dir.path().join("models/b.sql"), "select 1")?;
+        fs::write(dir.path().join("models/c.tsql"), "select 1")?;
+        fs::write(dir.path().join("models/staging/a.sql"), "select 1")?;
+        fs::write(dir.path().join("models/schema.yml"), "version: 2")?;
+        fs::write(dir.path().join("notes.txt"), "select 1")?;
+        let models = dir.path().join("models");
+        assert_eq!(
+            expand(slice::from_ref(&models), &selection(&["sql"]))?,
+            vec![models.join("b.sql"), models.join("staging/a.sql")]
+        );
+        assert_eq!(
+            expand(
+                &[
+                    dir.path().join("notes.txt"),
+                    models.join("**/*.*sql"),
+                    models.join("b.sql")
+                ],
+                &selection(&["sql"])
+            )?,
+            vec![
+                dir.path().join("notes.txt"),
+                models.join("b.sql"),
+                models.join("c.tsql"),
+                models.join("staging/a.sql")
+            ]
+        );
+        assert_eq!(
+            expand(slice::from_ref(&models), &selection(&["tsql"]))?,
+            vec![models.join("c.tsql")]
+        );
+        assert_eq!(
+            expand(&[models.join("[bc].{sql,tsql}")], &selection(&["sql"]))?,
+            vec![models.join("b.sql"), models.join("c.tsql")]
+        );
+        assert!(expand(&[models.join("*.psql")], &selection(&["sql"])).is_err());
+        assert!(expand(slice::from_ref(&models), &selection(&["psql"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_excluded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = fs::canonicalize(dir.path())?.join("repo");
+        fs::create_dir_all(root.join(".git"))?;
+        fs::create_dir_all(root.join("models/target"))?;
+        // Ignore files above the repository do not apply.
+        fs::write(root.join("../.gitignore"), "*.sql\n")?;
+        fs::create_dir_all(root.join("dumps"))?;
+        fs::write(
+            root.join(".gitignore"),
+            "target/\n*.gen.sql\n!keep.gen.sql\n",
+        )?;
+        fs::write(root.join("models/.formaignore"), "/legacy.sql\n")?;
+        for path in &[
+            "models/a.sql",
+            "models/legacy.sql",
+            "models/b.gen.sql",
+            "models/keep.gen.sql",
+            "models/target/c.sql",
+            "dumps/d.sql",
+        ] {
+            fs::write(root.join(path), "select 1")?;
+        }
+        let mut selection = Selection {
+            exclude: rules(&root, &["dumps/".to_owned()])?,
+            ..selection(&["sql"])
+        };
+        assert_eq!(
+            expand(slice::from_ref(&root), &selection)?,
+            vec![root.join("models/a.sql"), root.join("models/keep.gen.sql")]
+        );
+        assert_eq!(
+            expand(&[root.join("**/*.sql")], &selection)?,
+            vec![root.join("models/a.sql"), root.join("models/keep.gen.sql")]
+        );
+
+        // Files given by name are formatted regardless, unless exclusion is forced.
+        let named = [root.join("models/legacy.sql"), root.join("dumps/d.sql")];
+        assert_eq!(expand(&named, &selection)?, named.to_vec());
+        selection.force_exclude = true;
+        assert!(expand(&named, &selection)?.is_empty());
+        assert!(expand(&[root.join("models/target")], &selection)?.is_empty());
+        Ok(())
+    }
+}
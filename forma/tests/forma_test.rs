@@ -1,4 +1,5 @@
-use std::io::{Read, Write};
+use std::fs;
+use std::io::Write;
 
 use assert_cmd::Command;
 use tempfile::{self, NamedTempFile};
@@ -16,18 +17,43 @@ fn forma_stdin() {
     assert.success().stdout("select * from t1;\n");
 }
 
+#[test]
+fn forma_nearest_config() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("forma.toml"), "style = \"sqlstyle\"\n").unwrap();
+    fs::create_dir(dir.path().join("legacy")).unwrap();
+    fs::write(
+        dir.path().join("legacy/forma.toml"),
+        "keyword_case = \"lower\"\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("a.sql"), "select * from t1").unwrap();
+    fs::write(dir.path().join("legacy/b.sql"), "SELECT * FROM t1").unwrap();
+    // Each file is formatted with the config nearest to it, whichever input comes first.
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("legacy/b.sql")
+        .arg(".")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.sql")).unwrap(),
+        "SELECT * FROM t1;\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("legacy/b.sql")).unwrap(),
+        "select * from t1;\n"
+    );
+}
+
 #[test]
 fn forma_file() {
     let mut example_sql = NamedTempFile::new().unwrap();
     write!(example_sql, "SELECT * FROM t1").unwrap();
     let mut cmd = Command::cargo_bin("forma").unwrap();
     cmd.arg(example_sql.path()).assert().success();
-    let mut formatted = String::new();
-    example_sql
-        .reopen()
-        .unwrap()
-        .read_to_string(&mut formatted)
-        .unwrap();
+    // The file is replaced rather than rewritten, so it is read anew by its path.
+    let formatted = fs::read_to_string(example_sql.path()).unwrap();
     assert_eq!(formatted, "select * from t1;\n".to_string());
 }
 
@@ -36,4 +62,358 @@ fn forma_check() {
     let mut cmd = Command::cargo_bin("forma").unwrap();
     let assert = cmd.write_stdin("SELECT * FROM t1").arg("--check").assert();
     assert.failure().code(1);
+
+    // A formatted file passes as a whole, however many statements it holds.
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.sql"), "select 1;\nselect * from t1;\n").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("a.sql")
+        .arg("--check")
+        .assert()
+        .success();
+    fs::write(dir.path().join("a.sql"), "select 1;\nSELECT * FROM t1;\n").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("a.sql")
+        .arg("--check")
+        .assert()
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn forma_config() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("forma.toml"), "style = \"sqlstyle\"\n").unwrap();
+    let sql_path = dir.path().join("example.sql");
+    fs::write(&sql_path, "select * from t1").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.arg(&sql_path).assert().success();
+    assert_eq!(
+        fs::read_to_string(&sql_path).unwrap(),
+        "SELECT * FROM t1;\n".to_string()
+    );
+
+    // Flags take precedence over the config file.
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let assert = cmd
+        .current_dir(dir.path())
+        .write_stdin("SELECT * FROM t1")
+        .arg("--keyword-case")
+        .arg("lower")
+        .assert();
+    assert.success().stdout("select * from t1;\n");
+}
+
+#[test]
+fn forma_detected_dialect() {
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let assert = cmd
+        .write_stdin("SELECT a::INT FROM t1")
+        .arg("--style-edition")
+        .arg("0.4")
+        .arg("--verbose")
+        .assert();
+    assert
+        .success()
+        .stdout("select a::int from t1;\n")
+        .stderr("Detected dialect `postgresql`\n");
+
+    // Edition 0.3 keeps to the templated dialect.
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let assert = cmd
+        .write_stdin("SELECT a::INT FROM t1")
+        .arg("--verbose")
+        .assert();
+    assert
+        .success()
+        .stdout("select cast(a as int) from t1;\n")
+        .stderr("");
+
+    // Only directories within the repository hint at the dialect.
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("mysql/warehouse");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::create_dir_all(repo.join("postgres")).unwrap();
+    fs::write(repo.join("a.sql"), "select 1").unwrap();
+    fs::write(repo.join("postgres/b.sql"), "select 1").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let assert = cmd
+        .arg(repo.join("a.sql"))
+        .arg("--style-edition")
+        .arg("0.4")
+        .arg("--verbose")
+        .assert()
+        .success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+    assert!(
+        stderr.starts_with("Detected dialect `templated`"),
+        "{}",
+        stderr
+    );
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let assert = cmd
+        .arg(repo.join("postgres/b.sql"))
+        .arg("--style-edition")
+        .arg("0.4")
+        .arg("--verbose")
+        .assert()
+        .success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+    assert!(
+        stderr.starts_with("Detected dialect `postgresql`"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn forma_dbt() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("dbt_project.yml"),
+        "model-paths: [\"marts\"]\n",
+    )
+    .unwrap();
+    fs::create_dir(dir.path().join("marts")).unwrap();
+    let model_path = dir.path().join("marts/orders.sql");
+    fs::write(
+        &model_path,
+        "{{ config(materialized='table') }}\nSELECT id FROM {{ref(\"stg_orders\")}}",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path().join("marts"))
+        .arg("--dbt")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&model_path).unwrap(),
+        "{{ config(materialized='table') }}\nselect id from {{ ref('stg_orders') }};\n".to_string()
+    );
+}
+
+#[test]
+fn forma_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("models")).unwrap();
+    fs::write(dir.path().join("models/a.sql"), "SELECT 1").unwrap();
+    fs::write(dir.path().join("models/b.sql"), "SELECT (").unwrap();
+    fs::write(dir.path().join("models/c.txt"), "SELECT 1").unwrap();
+    fs::write(dir.path().join("d.psql"), "SELECT 1").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let output = cmd
+        .current_dir(dir.path())
+        .arg("models")
+        .arg("*.psql")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Formatted models/a.sql\n"));
+    assert!(stderr.contains("Unable to format models/b.sql: "));
+    assert!(stderr.contains("Formatted d.psql\n"));
+    assert!(stderr.contains("1 of 3 files could not be formatted"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("models/a.sql")).unwrap(),
+        "select 1;\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("models/c.txt")).unwrap(),
+        "SELECT 1"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("d.psql")).unwrap(),
+        "select 1;\n"
+    );
+}
+
+#[test]
+fn forma_exclude() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("forma.toml"), "exclude = [\"dumps/\"]\n").unwrap();
+    fs::write(dir.path().join(".formaignore"), "legacy.sql\n").unwrap();
+    fs::create_dir(dir.path().join("dumps")).unwrap();
+    fs::write(dir.path().join("dumps/dump.sql"), "SELECT 1").unwrap();
+    fs::write(dir.path().join("legacy.sql"), "SELECT 1").unwrap();
+    fs::write(dir.path().join("a.sql"), "SELECT 1").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path()).arg(".").assert().success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.sql")).unwrap(),
+        "select 1;\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("dumps/dump.sql")).unwrap(),
+        "SELECT 1"
+    );
+
+    // Files given by name are formatted, unless exclusion is forced.
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("dumps/dump.sql")
+        .arg("--force-exclude")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("dumps/dump.sql")).unwrap(),
+        "SELECT 1"
+    );
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("legacy.sql")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("legacy.sql")).unwrap(),
+        "select 1;\n"
+    );
+}
+
+#[test]
+fn forma_diff() {
+    let dir = tempfile::tempdir().unwrap();
+    let sql_path = dir.path().join("example.sql");
+    fs::write(&sql_path, "SELECT * FROM t1\n").unwrap();
+    let diff = concat!(
+        "--- example.sql (original)\n",
+        "+++ example.sql (formatted)\n",
+        "@@ -1 +1 @@\n",
+        "-SELECT * FROM t1\n",
+        "+select * from t1;\n",
+    );
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("example.sql")
+        .arg("--diff")
+        .assert()
+        .success()
+        .stdout(diff);
+    assert_eq!(fs::read_to_string(&sql_path).unwrap(), "SELECT * FROM t1\n");
+
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("example.sql")
+        .arg("--check")
+        .arg("--diff")
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(diff);
+
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.write_stdin("select * from t1;\n")
+        .arg("--check")
+        .arg("--diff")
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn forma_stdin_filepath() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("reports")).unwrap();
+    fs::write(
+        dir.path().join("reports/forma.toml"),
+        "keyword_case = \"upper\"\nstyle_edition = \"0.4\"\n",
+    )
+    .unwrap();
+    // The path need not exist, and is used to find the config and detect the dialect.
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    let assert = cmd
+        .current_dir(dir.path())
+        .write_stdin("select [Id] from [dbo].[Users]\nGO\n")
+        .arg("--stdin-filepath")
+        .arg("reports/users.tsql")
+        .arg("--verbose")
+        .assert();
+    assert
+        .success()
+        .stdout("SELECT [Id] FROM [dbo].[Users];\nGO\n")
+        .stderr("Detected dialect `mssql`\n");
+
+    fs::write(dir.path().join(".formaignore"), "legacy.sql\n").unwrap();
+    fs::write(dir.path().join("legacy.sql"), "SELECT 1").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.current_dir(dir.path())
+        .write_stdin("SELECT 1")
+        .arg("--stdin-filepath")
+        .arg("legacy.sql")
+        .arg("--force-exclude")
+        .assert()
+        .success()
+        .stdout("SELECT 1");
+}
+
+#[test]
+fn forma_outputs() {
+    let dir = tempfile::tempdir().unwrap();
+    let sql_path = dir.path().join("example.sql");
+    fs::write(&sql_path, "SELECT * FROM t1").unwrap();
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.arg(&sql_path)
+        .arg("--stdout")
+        .assert()
+        .success()
+        .stdout("select * from t1;\n");
+    assert_eq!(fs::read_to_string(&sql_path).unwrap(), "SELECT * FROM t1");
+
+    let output_path = dir.path().join("formatted.sql");
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.arg(&sql_path)
+        .arg("--output")
+        .arg(&output_path)
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(format!("Formatted {}\n", output_path.display()));
+    assert_eq!(fs::read_to_string(&sql_path).unwrap(), "SELECT * FROM t1");
+    assert_eq!(
+        fs::read_to_string(&output_path).unwrap(),
+        "select * from t1;\n"
+    );
+
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.write_stdin("SELECT a FROM t2")
+        .arg("--output")
+        .arg(&output_path)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(&output_path).unwrap(),
+        "select a from t2;\n"
+    );
+
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.arg(&sql_path)
+        .arg(&output_path)
+        .arg("--output")
+        .arg(dir.path().join("both.sql"))
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn forma_output_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let sql_path = dir.path().join("example.sql");
+    fs::write(&sql_path, "SELECT * FROM t1").unwrap();
+    let output_path = dir.path().join("formatted.sql");
+    let mut cmd = Command::cargo_bin("forma").unwrap();
+    cmd.arg(&sql_path)
+        .arg("--output")
+        .arg(&output_path)
+        .assert()
+        .success();
+    // A new output file is created like any other, here `example.sql`.
+    assert_eq!(
+        fs::metadata(&output_path).unwrap().permissions().mode(),
+        fs::metadata(&sql_path).unwrap().permissions().mode()
+    );
 }
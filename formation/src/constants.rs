@@ -1 +1,4 @@
-pub const NEST_FACTOR: isize = 2;
+pub const DEFAULT_MAX_WIDTH: usize = 100;
+pub const DEFAULT_INDENT_WIDTH: usize = 2;
+pub const RIVER_WIDTH: usize = 6;
+pub const ENTERPRISE_INDENT_WIDTH: usize = 4;
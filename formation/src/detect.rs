@@ -0,0 +1,204 @@
+//! Dialect detection
+//!
+//! When the dialect of some SQL isn't known, [`detect_dialect`] makes an educated guess. In order
+//! of precedence, it considers:
+//!
+//! 1. A header comment naming the dialect, e.g. `-- dialect: postgresql` or
+//!    `/* dialect: postgresql */`.
+//! 2. The path the SQL was read from, e.g. `report.tsql`, `daily.bq.sql` or `bigquery/daily.sql`.
+//!    Only the directories within the path's Git repository are considered, or outside of a
+//!    repository only the directory holding the file, so that e.g. a home directory named
+//!    `postgres` has no say.
+//! 3. Syntax peculiar to a dialect, e.g. `GO` lines, backtick quoting, `::` casts or `$$` quoting.
+//!
+//! Failing all of these, the default templated dialect is assumed.
+//!
+//! [`detect_dialect`]: fn.detect_dialect.html
+
+use std::fs;
+use std::path::Path;
+
+use crate::options::Dialect;
+
+/// File name suffixes which imply a dialect.
+const SUFFIXES: &[(&str, Dialect)] = &[
+    (".tsql", Dialect::MsSql),
+    (".mssql.sql", Dialect::MsSql),
+    (".bq.sql", Dialect::BigQuery),
+    (".bigquery.sql", Dialect::BigQuery),
+    (".pgsql", Dialect::PostgreSql),
+    (".psql", Dialect::PostgreSql),
+    (".pg.sql", Dialect::PostgreSql),
+    (".mysql", Dialect::MySql),
+    (".mysql.sql", Dialect::MySql),
+    (".sqlite", Dialect::Sqlite),
+    (".sqlite.sql", Dialect::Sqlite),
+    (".snowflake.sql", Dialect::Snowflake),
+    (".sf.sql", Dialect::Snowflake),
+];
+
+/// Directory names which imply a dialect.
+const DIRECTORIES: &[(&str, Dialect)] = &[
+    ("tsql", Dialect::MsSql),
+    ("mssql", Dialect::MsSql),
+    ("sqlserver", Dialect::MsSql),
+    ("bigquery", Dialect::BigQuery),
+    ("postgres", Dialect::PostgreSql),
+    ("postgresql", Dialect::PostgreSql),
+    ("mysql", Dialect::MySql),
+    ("sqlite", Dialect::Sqlite),
+    ("snowflake", Dialect::Snowflake),
+];
+
+/// Guesses the dialect of the given SQL, taking hints from the path it was read from, if any.
+pub fn detect_dialect(path: Option<&Path>, sql: &str) -> Dialect {
+    header_dialect(sql)
+        .or_else(|| path.and_then(path_dialect))
+        .or_else(|| syntax_dialect(sql))
+        .unwrap_or(Dialect::Templated)
+}
+
+/// Reads the dialect from the comments ahead of the SQL, be they line or block comments.
+fn header_dialect(sql: &str) -> Option<Dialect> {
+    let mut rest = sql.trim_start();
+    loop {
+        let comment = if let Some(comment) = rest.strip_prefix("--") {
+            let end = comment.find('\n').unwrap_or(comment.len());
+            rest = &comment[end..];
+            &comment[..end]
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment.find("*/")?;
+            rest = &comment[end + 2..];
+            &comment[..end]
+        } else {
+            return None;
+        };
+        if let Some(dialect) = comment.lines().find_map(dialect_directive) {
+            return Some(dialect);
+        }
+        rest = rest.trim_start();
+    }
+}
+
+/// Reads a line of a comment of the form `dialect: <name>`, ignoring any leading `-` or `*`.
+fn dialect_directive(line: &str) -> Option<Dialect> {
+    let comment = line.trim_start_matches(|c: char| c == '-' || c == '*' || c.is_whitespace());
+    let (key, value) = comment.split_at(comment.find(':')?);
+    if key.trim().eq_ignore_ascii_case("dialect") {
+        value[1..].trim().to_lowercase().parse().ok()
+    } else {
+        None
+    }
+}
+
+fn path_dialect(path: &Path) -> Option<Dialect> {
+    let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+    SUFFIXES
+        .iter()
+        .find(|(suffix, _)| file_name.ends_with(suffix))
+        .map(|&(_, dialect)| dialect)
+        .or_else(|| directory_dialect(path))
+}
+
+/// Takes the dialect from the directories holding the given path, up to the root of its Git
+/// repository. Outside of a repository, only the directory holding the path is considered.
+fn directory_dialect(path: &Path) -> Option<Dialect> {
+    // The path need not exist, e.g. that of an unsaved editor buffer.
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+    let root = dirs
+        .iter()
+        .position(|dir| dir.join(".git").exists())
+        .unwrap_or(1);
+    dirs.iter().take(root).find_map(|dir| {
+        let name = dir.file_name()?.to_string_lossy().to_lowercase();
+        DIRECTORIES
+            .iter()
+            .find(|(directory, _)| name == *directory)
+            .map(|&(_, dialect)| dialect)
+    })
+}
+
+fn syntax_dialect(sql: &str) -> Option<Dialect> {
+    // A line of its own, e.g. `GO` or `DELIMITER $$`.
+    let has_line = |keyword: &str| {
+        sql.lines().any(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            matches!(words.as_slice(), [first] | [first, _] if first.eq_ignore_ascii_case(keyword))
+        })
+    };
+    if ["{{", "{%", "{#"].iter().any(|tag| sql.contains(tag)) {
+        Some(Dialect::Templated)
+    } else if has_line("go") {
+        Some(Dialect::MsSql)
+    } else if has_line("delimiter") {
+        Some(Dialect::MySql)
+    } else if let Some(quoted) = sql.split('`').nth(1) {
+        // BigQuery quotes whole paths, e.g. `project.dataset.table`, where MySQL quotes each part.
+        if quoted.contains('.') {
+            Some(Dialect::BigQuery)
+        } else {
+            Some(Dialect::MySql)
+        }
+    } else if sql.contains("::") || sql.contains("$$") {
+        Some(Dialect::PostgreSql)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_detect_dialect() {
+        let sql = "select * from t1";
+        assert_eq!(detect_dialect(None, sql), Dialect::Templated);
+        assert_eq!(
+            detect_dialect(None, "-- dialect: Snowflake\nselect a::int from t1"),
+            Dialect::Snowflake
+        );
+        assert_eq!(
+            detect_dialect(Some(Path::new("reports/daily.tsql")), sql),
+            Dialect::MsSql
+        );
+        assert_eq!(
+            detect_dialect(Some(Path::new("models/daily.BQ.sql")), sql),
+            Dialect::BigQuery
+        );
+        assert_eq!(
+            detect_dialect(Some(Path::new("warehouse/postgres/daily.sql")), sql),
+            Dialect::PostgreSql
+        );
+        // Directories outside of the project have no say.
+        assert_eq!(
+            detect_dialect(Some(Path::new("/home/postgres/work/daily.sql")), sql),
+            Dialect::Templated
+        );
+        assert_eq!(
+            detect_dialect(
+                None,
+                "/*\n * Daily totals.\n * dialect: mysql\n */\nselect 1"
+            ),
+            Dialect::MySql
+        );
+        assert_eq!(
+            detect_dialect(Some(Path::new("daily.sql")), "select 1\nGO\n"),
+            Dialect::MsSql
+        );
+        assert_eq!(
+            detect_dialect(None, "select * from `project.dataset.table`"),
+            Dialect::BigQuery
+        );
+        assert_eq!(
+            detect_dialect(None, "select * from `users`"),
+            Dialect::MySql
+        );
+        assert_eq!(
+            detect_dialect(None, "select a::int from t1"),
+            Dialect::PostgreSql
+        );
+    }
+}
@@ -1,3 +1,158 @@
+mod bigquery;
+mod mysql;
+mod snowflake;
+mod sqlite;
 mod templated;
 
+use sqlparser::dialect::{
+    AnsiDialect, Dialect as ParserDialect, GenericDialect, MsSqlDialect, PostgreSqlDialect,
+};
+
+use crate::options::Dialect;
+
+pub use crate::dialect::bigquery::BigQueryDialect;
+pub use crate::dialect::mysql::MySqlDialect;
+pub use crate::dialect::snowflake::SnowflakeDialect;
+pub use crate::dialect::sqlite::SqliteDialect;
 pub use crate::dialect::templated::TemplatedDialect;
+
+/// How SQL of a particular dialect is rendered.
+pub struct Profile {
+    /// Whether quoted identifiers keep their case. Quoting makes an identifier case-sensitive in
+    /// most databases, such that lowercasing it would change which object it refers to.
+    pub preserve_quoted_case: bool,
+    /// Whether casts keep the form they were written in, rather than all being written as
+    /// `cast(x as type)`.
+    pub preserve_cast_style: bool,
+    /// The words the dialect reserves beyond sqlparser's keywords, which are shared by every
+    /// dialect. An alias which is either is always written after `as`.
+    pub reserved_words: &'static [&'static str],
+}
+
+/// Returns the parser dialect for the given dialect.
+pub fn parser_dialect(dialect: Dialect) -> Box<dyn ParserDialect> {
+    match dialect {
+        Dialect::Templated => Box::new(TemplatedDialect {}),
+        Dialect::Generic => Box::new(GenericDialect {}),
+        Dialect::Ansi => Box::new(AnsiDialect {}),
+        Dialect::PostgreSql => Box::new(PostgreSqlDialect {}),
+        Dialect::MySql => Box::new(MySqlDialect {}),
+        Dialect::MsSql => Box::new(MsSqlDialect {}),
+        Dialect::Sqlite => Box::new(SqliteDialect {}),
+        Dialect::BigQuery => Box::new(BigQueryDialect {}),
+        Dialect::Snowflake => Box::new(SnowflakeDialect {}),
+    }
+}
+
+/// Returns the rendering profile for the given dialect.
+pub fn profile(dialect: Dialect) -> Profile {
+    match dialect {
+        // Lowercasing every identifier is what the templated dialect has always done.
+        Dialect::Templated => Profile {
+            preserve_quoted_case: false,
+            preserve_cast_style: false,
+            reserved_words: &[],
+        },
+        Dialect::PostgreSql => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: true,
+            reserved_words: &[
+                "ANALYSE",
+                "ANALYZE",
+                "DO",
+                "ILIKE",
+                "ISNULL",
+                "NOTNULL",
+                "RETURNING",
+            ],
+        },
+        Dialect::Snowflake => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: true,
+            reserved_words: &[
+                "ILIKE",
+                "INCREMENT",
+                "MINUS",
+                "QUALIFY",
+                "REGEXP",
+                "RLIKE",
+                "SAMPLE",
+            ],
+        },
+        Dialect::MySql => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: false,
+            reserved_words: &[
+                "ANALYZE",
+                "DIV",
+                "DUAL",
+                "FORCE",
+                "HIGH_PRIORITY",
+                "IGNORE",
+                "ITERATE",
+                "KEYS",
+                "LEAVE",
+                "LOCK",
+                "LOOP",
+                "LOW_PRIORITY",
+                "REGEXP",
+                "REPEAT",
+                "REPLACE",
+                "RLIKE",
+                "SEPARATOR",
+                "SPATIAL",
+                "SQL_CALC_FOUND_ROWS",
+                "STRAIGHT_JOIN",
+                "USE",
+                "XOR",
+            ],
+        },
+        Dialect::MsSql => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: false,
+            reserved_words: &[
+                "BROWSE",
+                "BULK",
+                "CHECKPOINT",
+                "DBCC",
+                "OPENROWSET",
+                "OPTION",
+                "PIVOT",
+                "PRINT",
+                "PROC",
+                "RAISERROR",
+                "READTEXT",
+                "TEXTSIZE",
+                "TRAN",
+                "UNPIVOT",
+                "WAITFOR",
+            ],
+        },
+        Dialect::Sqlite => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: false,
+            reserved_words: &["GLOB", "INDEXED", "ISNULL", "NOTNULL", "REGEXP"],
+        },
+        Dialect::BigQuery => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: false,
+            reserved_words: &[
+                "ASSERT_ROWS_MODIFIED",
+                "DEFINE",
+                "ENUM",
+                "EXCLUDE",
+                "HASH",
+                "IGNORE",
+                "LOOKUP",
+                "PROTO",
+                "QUALIFY",
+                "STRUCT",
+            ],
+        },
+        Dialect::Generic | Dialect::Ansi => Profile {
+            preserve_quoted_case: true,
+            preserve_cast_style: false,
+            reserved_words: &[],
+        },
+    }
+}
@@ -0,0 +1,19 @@
+use sqlparser::dialect::Dialect;
+
+#[derive(Debug)]
+pub struct BigQueryDialect {}
+
+impl Dialect for BigQueryDialect {
+    // Backticks quote both identifiers and whole paths, e.g. `project.dataset.table`.
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        ch == '`'
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        ch.is_ascii_lowercase() || ch.is_ascii_uppercase() || ch == '_'
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        ch.is_ascii_lowercase() || ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_'
+    }
+}
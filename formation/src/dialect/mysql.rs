@@ -0,0 +1,26 @@
+use sqlparser::dialect::Dialect;
+
+#[derive(Debug)]
+pub struct MySqlDialect {}
+
+impl Dialect for MySqlDialect {
+    // Backticks quote identifiers, which sqlparser's own MySQL dialect does not accept. `"` quotes
+    // strings, unless `ANSI_QUOTES` is set, and these are masked ahead of tokenizing.
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        ch == '`'
+    }
+
+    // `@` starts user variables, e.g. `@total`, and system variables, e.g. `@@sql_mode`.
+    fn is_identifier_start(&self, ch: char) -> bool {
+        ch.is_ascii_lowercase()
+            || ch.is_ascii_uppercase()
+            || ch == '_'
+            || ch == '$'
+            || ch == '@'
+            || ch >= '\u{0080}'
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        self.is_identifier_start(ch) || ch.is_ascii_digit()
+    }
+}
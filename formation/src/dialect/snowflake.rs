@@ -0,0 +1,18 @@
+use sqlparser::dialect::Dialect;
+
+#[derive(Debug)]
+pub struct SnowflakeDialect {}
+
+impl Dialect for SnowflakeDialect {
+    fn is_identifier_start(&self, ch: char) -> bool {
+        ch.is_ascii_lowercase() || ch.is_ascii_uppercase() || ch == '_'
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        ch.is_ascii_lowercase()
+            || ch.is_ascii_uppercase()
+            || ch.is_ascii_digit()
+            || ch == '$'
+            || ch == '_'
+    }
+}
@@ -0,0 +1,23 @@
+use sqlparser::dialect::Dialect;
+
+#[derive(Debug)]
+pub struct SqliteDialect {}
+
+impl Dialect for SqliteDialect {
+    // SQLite accepts MySQL's backticks and T-SQL's brackets alongside double quotes.
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        ch == '"' || ch == '`' || ch == '['
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        ch.is_ascii_lowercase()
+            || ch.is_ascii_uppercase()
+            || ch == '_'
+            || ch == '$'
+            || ch >= '\u{0080}'
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        self.is_identifier_start(ch) || ch.is_ascii_digit()
+    }
+}
@@ -0,0 +1,143 @@
+//! Differences between SQL and its formatted form
+//!
+//! Rather than apply formatting, [`unified_diff`] describes the changes it would make as a unified
+//! diff, in the form `diff -u` writes, e.g. to review in CI what `check` would otherwise only
+//! reject.
+//!
+//! ```
+//! use formation::{diff::unified_diff, format};
+//!
+//! let sql = "SELECT * FROM t1";
+//! let formatted = format(sql, false, 100).unwrap().concat();
+//! assert_eq!(
+//!     unified_diff(sql, &formatted, "query.sql").unwrap(),
+//!     concat!(
+//!         "--- query.sql (original)\n",
+//!         "+++ query.sql (formatted)\n",
+//!         "@@ -1 +1 @@\n",
+//!         "-SELECT * FROM t1\n",
+//!         "\\ No newline at end of file\n",
+//!         "+select * from t1;\n",
+//!     )
+//! );
+//! ```
+//!
+//! [`unified_diff`]: fn.unified_diff.html
+
+use std::time::Duration;
+
+use similar::TextDiff;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// How long to search for the smallest diff before settling for a larger one.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Returns a unified diff of the changes between `original` and `formatted`, with headers naming
+/// the given path, or `None` if they are the same.
+///
+/// Lines are compared with Myers' algorithm, which needs memory in proportion to the length of the
+/// text and the number of changes rather than the product of their lengths. Should the texts have
+/// too much changed to compare in time, the diff is correct but may be larger than need be.
+pub fn unified_diff(original: &str, formatted: &str, path: &str) -> Option<String> {
+    if original == formatted {
+        return None;
+    }
+    let diff = TextDiff::configure()
+        .timeout(TIMEOUT)
+        .diff_lines(original, formatted);
+    let diff = diff
+        .unified_diff()
+        .context_radius(CONTEXT)
+        .header(
+            &format!("{} (original)", path),
+            &format!("{} (formatted)", path),
+        )
+        .to_string();
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(unified_diff("select 1;\n", "select 1;\n", "a.sql"), None);
+
+        let comments = |range: Range<usize>| -> String {
+            range.map(|line| format!("-- {}\n", line)).collect()
+        };
+        let original = comments(1..5) + "SELECT 1;\n" + &comments(6..13) + "SELECT 2;\n";
+        let formatted = comments(1..5) + "select 1;\n" + &comments(6..13) + "select\n  2;\n";
+        assert_eq!(
+            unified_diff(&original, &formatted, "a.sql").unwrap(),
+            concat!(
+                "--- a.sql (original)\n",
+                "+++ a.sql (formatted)\n",
+                "@@ -2,7 +2,7 @@\n",
+                " -- 2\n",
+                " -- 3\n",
+                " -- 4\n",
+                "-SELECT 1;\n",
+                "+select 1;\n",
+                " -- 6\n",
+                " -- 7\n",
+                " -- 8\n",
+                "@@ -10,4 +10,5 @@\n",
+                " -- 10\n",
+                " -- 11\n",
+                " -- 12\n",
+                "-SELECT 2;\n",
+                "+select\n",
+                "+  2;\n",
+            )
+        );
+
+        // Changes whose context meets share a hunk.
+        assert_eq!(
+            unified_diff("a\nb\nc\nd\n", "A\nb\nc\nD\n", "a.sql").unwrap(),
+            concat!(
+                "--- a.sql (original)\n",
+                "+++ a.sql (formatted)\n",
+                "@@ -1,4 +1,4 @@\n",
+                "-a\n",
+                "+A\n",
+                " b\n",
+                " c\n",
+                "-d\n",
+                "+D\n",
+            )
+        );
+
+        assert_eq!(
+            unified_diff("", "select 1;\n", "a.sql").unwrap(),
+            concat!(
+                "--- a.sql (original)\n",
+                "+++ a.sql (formatted)\n",
+                "@@ -0,0 +1 @@\n",
+                "+select 1;\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_large() {
+        let original: String = (0..6000)
+            .map(|line| format!("SELECT {};\n", line))
+            .collect();
+        let formatted: String = (0..6000)
+            .map(|line| match line % 100 {
+                0 => format!("select {};\n", line),
+                _ => format!("SELECT {};\n", line),
+            })
+            .collect();
+        let diff = unified_diff(&original, &formatted, "a.sql").unwrap();
+        assert_eq!(diff.matches("\n@@ ").count(), 60);
+        assert_eq!(diff.matches("\n-SELECT").count(), 60);
+    }
+}
@@ -1,29 +1,231 @@
 mod body;
 mod common;
 mod expr;
+mod procedural;
 mod query;
+mod statement;
 
-use crate::error;
-use sqlparser::ast::Statement;
+use std::fmt;
+use std::ops::Deref;
 
+use crate::error::{self, FormaError};
+use sqlparser::ast::{DataType, Expr, Ident, ObjectName, Statement};
+
+use crate::dialect::{profile, Profile};
 use crate::doc::common::FormaDoc;
+use crate::doc::procedural::block_doc;
 use crate::doc::query::query_doc;
+use crate::doc::statement::{insert_doc, set_variable_doc};
+use crate::mask::Masks;
+use crate::options::{Dialect, FormatOptions, IndentStyle, StyleEdition};
+use crate::procedural::Block;
+use crate::scan::{CastStyle, LimitStyle, Scan, Syntax};
+
+/// The options consulted while rendering: the caller's `FormatOptions` and their dialect's profile,
+/// along with what has been gathered by scanning the source SQL.
+pub struct RenderOptions<'a> {
+    options: &'a FormatOptions,
+    scan: Scan,
+    masks: Masks,
+    profile: Profile,
+}
+
+impl<'a> RenderOptions<'a> {
+    /// Creates render options from the format options, what was recovered by scanning the source
+    /// SQL and the syntax masked from the parser.
+    pub fn new(options: &'a FormatOptions, scan: Scan, masks: Masks) -> Self {
+        RenderOptions {
+            options,
+            scan,
+            masks,
+            profile: profile(options.dialect),
+        }
+    }
+
+    /// Returns the rendering profile of the dialect being formatted.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Returns the given alias as written, along with whether it was introduced with `as` in the
+    /// source SQL.
+    pub fn unmark_alias(&self, alias: Ident) -> (Ident, bool) {
+        self.scan.unmark_alias(alias)
+    }
+
+    /// Returns the data type of a cast as written, along with how the cast was written in the
+    /// source SQL.
+    pub fn unmark_cast(&self, data_type: DataType) -> (DataType, CastStyle) {
+        self.scan.unmark_cast(data_type)
+    }
+
+    /// Restores the syntax masked from the parser within the given text.
+    pub fn unmask(&self, text: &str) -> String {
+        self.masks.unmask(text, false)
+    }
+
+    /// Restores the syntax masked from the parser within the given lowercased identifier.
+    ///
+    /// Before edition 0.4, the templated dialect lowercases the templates and placeholders it
+    /// reads as identifiers, e.g. `{{Date}}` or `@Var`, as it did before they were masked.
+    pub fn unmask_identifier(&self, text: &str) -> String {
+        let lowercase =
+            self.dialect == Dialect::Templated && self.style_edition < StyleEdition::V0_4;
+        self.masks.unmask(text, lowercase)
+    }
+
+    /// Returns the offset of a `limit` as written, along with how the `limit` was written in the
+    /// source SQL.
+    pub fn unmark_offset(&self, offset: Expr) -> (Expr, LimitStyle) {
+        self.scan.unmark_offset(offset)
+    }
+
+    /// Returns the operator which the given operand of a `|` operator marks, if any.
+    pub fn unmark_operator(&self, operand: &Expr) -> Option<&'static str> {
+        self.scan.unmark_operator(operand)
+    }
+
+    /// Returns the syntax which a call of the function of the given name stands in for, if any.
+    pub fn unmark_function(&self, name: &ObjectName) -> Option<Syntax> {
+        self.scan.unmark_function(name)
+    }
+
+    /// Returns the data type as written which the given function name stands in for, if any.
+    pub fn unmark_type(&self, name: &ObjectName) -> Option<&str> {
+        self.scan.unmark_type(name)
+    }
+}
+
+impl Deref for RenderOptions<'_> {
+    type Target = FormatOptions;
+
+    fn deref(&self) -> &FormatOptions {
+        self.options
+    }
+}
 
 /// Transforms the given `Statement` into an `RcDoc`.
-fn statement_doc<'a>(statement: Statement) -> FormaDoc<'a> {
+fn statement_doc<'a>(statement: Statement, options: &RenderOptions) -> FormaDoc<'a> {
     match statement {
         // Select statement.
-        Statement::Query(query) => query_doc(*query),
+        Statement::Query(query) => query_doc(*query, options),
+        // Insert statement, e.g. `insert into t1 (a) values (1)`.
+        Statement::Insert {
+            table_name,
+            columns,
+            source,
+        } => insert_doc(table_name, columns, *source, options),
+        // Variable assignment, e.g. `set @x = 1`.
+        Statement::SetVariable {
+            local,
+            variable,
+            value,
+        } => set_variable_doc(local, variable, value, options),
         // TODO: Match remaining statement variants.
-        _ => unreachable!("Unhandled `Statement` variant"),
+        _ => unreachable!("`render_block` rejects unsupported statements"),
+    }
+}
+
+/// Returns an error naming the first statement within the `Block` which `statement_doc` cannot
+/// lay out.
+fn check_supported(block: &Block) -> error::Result<()> {
+    match block {
+        Block::Statement(Statement::Query(_))
+        | Block::Statement(Statement::Insert { .. })
+        | Block::Statement(Statement::SetVariable { .. }) => Ok(()),
+        Block::Statement(statement) => Err(FormaError::UnsupportedStatement(statement.to_string())),
+        Block::Begin { body, .. } | Block::While { body, .. } => {
+            body.iter().try_for_each(check_supported)
+        }
+        Block::If {
+            branches,
+            otherwise,
+            ..
+        } => branches
+            .iter()
+            .flat_map(|(_, body)| body)
+            .chain(otherwise.iter().flatten())
+            .try_for_each(check_supported),
+        Block::Declare(_)
+        | Block::Assign { .. }
+        | Block::Set { .. }
+        | Block::Return(_)
+        | Block::Insert { .. } => Ok(()),
+    }
+}
+
+/// Renders the `Block`, i.e. a statement, in accordance with the provided options.
+/// Errors if the block holds a statement that cannot be laid out.
+pub fn render_block(block: Block, options: &RenderOptions) -> error::Result<String> {
+    check_supported(&block)?;
+    let mut writer = IndentWriter::new(options.indent_style, options.indent_width);
+    block_doc(block, options)
+        .render_fmt(options.max_width, &mut writer)
+        .expect("`IndentWriter` never fails");
+    Ok(writer.finish())
+}
+
+/// Collects the rendered document, writing the indentation of each line in the configured style.
+///
+/// The document is always laid out with spaces, which is what lets a tab count as `indent_width`
+/// columns when fitting lines to the maximum width. Only the indentation the renderer writes
+/// after breaking a line is rewritten, one tab per `indent_width` spaces, so that text spanning
+/// lines, e.g. a multi-line string, is kept as written. Any remainder that does not make up a
+/// full level is left as spaces.
+struct IndentWriter {
+    output: String,
+    indent_style: IndentStyle,
+    indent_width: usize,
+    /// The width of the indentation being written, following a line break.
+    indent: Option<usize>,
+}
+
+impl IndentWriter {
+    fn new(indent_style: IndentStyle, indent_width: usize) -> Self {
+        IndentWriter {
+            output: String::new(),
+            indent_style,
+            indent_width: indent_width.max(1),
+            indent: None,
+        }
+    }
+
+    fn write_indent(&mut self) {
+        if let Some(indent) = self.indent.take() {
+            match self.indent_style {
+                IndentStyle::Spaces => self.output.push_str(&" ".repeat(indent)),
+                IndentStyle::Tabs => {
+                    self.output
+                        .push_str(&"\t".repeat(indent / self.indent_width));
+                    self.output
+                        .push_str(&" ".repeat(indent % self.indent_width));
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.write_indent();
+        self.output
     }
 }
 
-/// Renders the `Statement` in accordance with the provided maximum width.
-pub fn render_statement(statement: Statement, max_width: usize) -> error::Result<String> {
-    let mut bs = Vec::new();
-    statement_doc(statement).render(max_width, &mut bs)?;
-    Ok(String::from_utf8(bs)?)
+impl fmt::Write for IndentWriter {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        if let Some(indent) = self.indent.as_mut() {
+            if text.bytes().all(|byte| byte == b' ') {
+                *indent += text.len();
+                return Ok(());
+            }
+        }
+        self.write_indent();
+        self.output.push_str(text);
+        // The renderer breaks a line by writing a lone newline, followed by the indentation.
+        if text == "\n" {
+            self.indent = Some(0);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -35,7 +237,7 @@ mod tests {
     const MAX_WIDTH: usize = 100;
 
     #[test]
-    fn test_render_statement() {
+    fn test_render_block() {
         let statement = Statement::Query(Box::new(Query {
             body: SetExpr::Select(Box::new(Select {
                 distinct: false,
@@ -55,8 +257,42 @@ mod tests {
             order_by: vec![],
         }));
         assert_eq!(
-            render_statement(statement, MAX_WIDTH).unwrap(),
+            render_block(
+                Block::Statement(statement),
+                &RenderOptions::new(
+                    &FormatOptions {
+                        max_width: MAX_WIDTH,
+                        ..FormatOptions::default()
+                    },
+                    Scan::new(&[], Dialect::Templated).0,
+                    Masks::new("")
+                )
+            )
+            .unwrap(),
             "select 42".to_owned()
         );
     }
+
+    #[test]
+    fn test_indent_writer() {
+        let mut writer = IndentWriter::new(IndentStyle::Tabs, 2);
+        for text in &[
+            "select",
+            "\n",
+            "  ",
+            "a,",
+            "\n",
+            "    ",
+            "'b\n    c'",
+            "\n",
+            "   ",
+            "d",
+        ] {
+            fmt::Write::write_str(&mut writer, text).unwrap();
+        }
+        assert_eq!(
+            writer.finish(),
+            "select\n\ta,\n\t\t'b\n    c'\n\t d".to_owned()
+        );
+    }
 }
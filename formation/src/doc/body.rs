@@ -1,44 +1,60 @@
 use pretty::RcDoc;
 use sqlparser::ast::{
-    Expr, Join, JoinConstraint, JoinOperator, Select, SelectItem, SetExpr, TableAlias, TableFactor,
-    TableWithJoins, Top,
+    Expr, Function, Ident, Join, JoinConstraint, JoinOperator, Select, SelectItem, SetExpr,
+    TableAlias, TableFactor, TableWithJoins, Top,
 };
 
-use crate::constants::NEST_FACTOR;
-use crate::doc::common::{ident_doc, interweave_comma, parenthenized, Exprs, FormaDoc};
+use crate::doc::common::{
+    as_doc, clause_line, clause_separator, ident_doc, interweave_comma, keyword, lowercase_ident,
+    parenthenized, text, Exprs, FormaDoc,
+};
 use crate::doc::expr::expr_doc;
 use crate::doc::query::query_doc;
+use crate::doc::RenderOptions;
+use crate::options::Layout;
+use crate::scan::Syntax;
 
 /// Transforms the given `SetExpr` into an `RcDoc`.
-pub fn body_doc<'a>(body: SetExpr) -> FormaDoc<'a> {
+pub fn body_doc<'a>(body: SetExpr, options: &RenderOptions) -> FormaDoc<'a> {
     match body {
         SetExpr::Select(box Select {
             distinct,
             top,
-            projection,
+            mut projection,
             from,
             selection,
             group_by,
             having,
         }) => {
+            let (having, qualify) = qualify(having, options);
             // Distinct.
             if distinct {
-                RcDoc::text("select distinct")
+                keyword("select distinct", options)
             } else {
-                RcDoc::text("select")
+                keyword("select", options)
             }
+            // PostgreSQL's `distinct on (...)`.
+            .append(match distinct_on(&mut projection, options) {
+                Some(exprs) => RcDoc::space()
+                    .append(keyword("on", options))
+                    .append(RcDoc::space())
+                    .append(exprs_doc(exprs, options)),
+                None => RcDoc::nil(),
+            })
             // Top.
-            .append(top_doc(top))
+            .append(top_doc(top, options))
             // Projection.
-            .append(projection_doc(projection))
+            .append(projection_doc(projection, options))
             // From.
-            .append(from_doc(from))
+            .append(from_doc(from, options))
             // Selection.
-            .append(selection_doc(selection))
+            .append(selection_doc(selection, options))
             // Group By.
-            .append(group_by_doc(group_by))
+            .append(group_by_doc(group_by, options))
             // Having.
-            .append(having_doc(having))
+            .append(condition_doc("having", having, options))
+            // Snowflake's `qualify`.
+            .append(condition_doc("qualify", qualify, options))
         }
 
         SetExpr::SetOperation {
@@ -46,195 +62,352 @@ pub fn body_doc<'a>(body: SetExpr) -> FormaDoc<'a> {
             all,
             left,
             right,
-        } => body_doc(*left)
-            .append(
-                RcDoc::line().append(RcDoc::text(op.to_string().to_lowercase()).append(if all {
-                    RcDoc::space().append(RcDoc::text("all"))
-                } else {
-                    RcDoc::nil()
-                })),
-            )
-            .append(RcDoc::line())
-            .append(body_doc(*right)),
+        } => {
+            let op = op.to_string();
+            body_doc(*left, options)
+                .append(
+                    clause_line(&op, options).append(keyword(&op, options).append(if all {
+                        RcDoc::space().append(keyword("all", options))
+                    } else {
+                        RcDoc::nil()
+                    })),
+                )
+                .append(RcDoc::line())
+                .append(body_doc(*right, options))
+        }
 
         // Parenthensized query, i.e. order evaluation enforcement.
-        SetExpr::Query(query) => parenthenized(query_doc(*query)),
+        SetExpr::Query(query) => parenthenized(query_doc(*query, options), options),
 
         // Values, such as insert values for the given expression.
         SetExpr::Values(values) => {
-            RcDoc::text("values")
+            keyword("values", options)
                 .append(RcDoc::space())
                 .append(RcDoc::concat(values.0.into_iter().map(|row| {
-                    parenthenized(interweave_comma(row.into_iter().map(expr_doc)))
+                    parenthenized(
+                        interweave_comma(
+                            row.into_iter().map(|expr| expr_doc(expr, options)),
+                            options,
+                        ),
+                        options,
+                    )
                 })))
         }
     }
 }
 
-fn top_doc<'a>(top: Option<Top>) -> FormaDoc<'a> {
+/// Removes PostgreSQL's `distinct on (...)` from the start of the given projection, where it is
+/// marked, returning its expressions.
+fn distinct_on(projection: &mut Vec<SelectItem>, options: &RenderOptions) -> Option<Exprs> {
+    match projection.first() {
+        Some(SelectItem::UnnamedExpr(Expr::Function(Function { name, .. })))
+            if options.unmark_function(name) == Some(Syntax::DistinctOn) =>
+        {
+            match projection.remove(0) {
+                SelectItem::UnnamedExpr(Expr::Function(Function { args, .. })) => Some(args),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn top_doc<'a>(top: Option<Top>, options: &RenderOptions) -> FormaDoc<'a> {
     if let Some(Top {
         with_ties,
         percent,
         quantity,
     }) = top
     {
-        let extension = if with_ties { " with ties" } else { "" };
+        let case = |keyword: &str| options.keyword_case.apply(keyword);
+        let extension = if with_ties {
+            case(" with ties")
+        } else {
+            String::new()
+        };
         if let Some(quantity) = quantity {
-            let percent = if percent { " percent" } else { "" };
-            RcDoc::text(format!(" top ({}{}{})", quantity, percent, extension))
+            let percent = if percent {
+                case(" percent")
+            } else {
+                String::new()
+            };
+            text(
+                format!(" {} ({}{}{})", case("top"), quantity, percent, extension),
+                options,
+            )
         } else {
-            RcDoc::text(format!(" top{}", extension))
+            RcDoc::text(format!(" {}{}", case("top"), extension))
         }
     } else {
         RcDoc::nil()
     }
 }
 
-fn projection_doc<'a>(projection: Vec<SelectItem>) -> FormaDoc<'a> {
-    RcDoc::line().nest(NEST_FACTOR).append(
-        interweave_comma(projection.into_iter().map(|select_item| {
-            match select_item {
-                SelectItem::ExprWithAlias { expr, alias } => expr_doc(expr)
-                    .append(RcDoc::space())
-                    .append(RcDoc::text("as"))
-                    .append(RcDoc::space())
-                    .append(RcDoc::text(alias.to_string())),
-                SelectItem::QualifiedWildcard(object_name) => RcDoc::text(object_name.to_string()),
-                SelectItem::UnnamedExpr(expr) => expr_doc(expr),
-                SelectItem::Wildcard => RcDoc::text("*"),
-            }
-        }))
-        .nest(NEST_FACTOR)
+fn projection_doc<'a>(projection: Vec<SelectItem>, options: &RenderOptions) -> FormaDoc<'a> {
+    clause_separator(options).append(
+        interweave_comma(
+            projection
+                .into_iter()
+                .map(|select_item| select_item_doc(select_item, options)),
+            options,
+        )
+        .nest(options.clause_nest())
         .group(),
     )
 }
 
-fn from_doc<'a>(from: Vec<TableWithJoins>) -> FormaDoc<'a> {
+/// Transforms an item of a projection or a `returning` clause, e.g. `a + 1 as b`, into an `RcDoc`.
+pub fn select_item_doc<'a>(select_item: SelectItem, options: &RenderOptions) -> FormaDoc<'a> {
+    match select_item {
+        SelectItem::ExprWithAlias { expr, alias } => {
+            let (alias, written) = options.unmark_alias(alias);
+            expr_doc(expr, options)
+                .append(RcDoc::space())
+                .append(as_doc(&alias, written, options.column_alias, options))
+                .append(text(alias.to_string(), options))
+        }
+        SelectItem::QualifiedWildcard(object_name) => text(object_name.to_string(), options),
+        SelectItem::UnnamedExpr(expr) => expr_doc(expr, options),
+        SelectItem::Wildcard => RcDoc::text("*"),
+    }
+}
+
+fn from_doc<'a>(from: Vec<TableWithJoins>, options: &RenderOptions) -> FormaDoc<'a> {
     if !from.is_empty() {
-        RcDoc::line().append(RcDoc::text("from")).append(
-            RcDoc::line().nest(NEST_FACTOR).append(
-                interweave_comma(from.into_iter().map(|TableWithJoins { joins, relation }| {
-                    relation_doc(relation).append(if !joins.is_empty() {
-                        RcDoc::line().append(RcDoc::intersperse(
-                            joins.into_iter().map(join_doc),
-                            RcDoc::line(),
-                        ))
-                    } else {
-                        RcDoc::nil()
-                    })
-                }))
-                .nest(NEST_FACTOR)
-                .group(),
-            ),
-        )
+        clause_line("from", options)
+            .append(keyword("from", options))
+            .append(
+                clause_separator(options).append(
+                    interweave_comma(
+                        from.into_iter().map(|TableWithJoins { joins, relation }| {
+                            relation_doc(relation, options).append(if !joins.is_empty() {
+                                RcDoc::line().append(RcDoc::intersperse(
+                                    joins.into_iter().map(|join| join_doc(join, options)),
+                                    RcDoc::line(),
+                                ))
+                            } else {
+                                RcDoc::nil()
+                            })
+                        }),
+                        options,
+                    )
+                    .nest(options.clause_nest())
+                    .group(),
+                ),
+            )
     } else {
         RcDoc::nil()
     }
 }
 
-fn group_by_doc<'a>(group_by: Exprs) -> FormaDoc<'a> {
+fn group_by_doc<'a>(group_by: Exprs, options: &RenderOptions) -> FormaDoc<'a> {
     if !group_by.is_empty() {
-        RcDoc::line()
-            .append(RcDoc::text("group by").append(RcDoc::line().nest(NEST_FACTOR)))
+        clause_line("group by", options)
+            .append(keyword("group by", options).append(clause_separator(options)))
             .append(
-                interweave_comma(group_by.into_iter().map(expr_doc))
-                    .nest(NEST_FACTOR)
-                    .group(),
+                interweave_comma(
+                    group_by.into_iter().map(|expr| expr_doc(expr, options)),
+                    options,
+                )
+                .nest(options.clause_nest())
+                .group(),
             )
     } else {
         RcDoc::nil()
     }
 }
 
-fn selection_doc<'a>(selection: Option<Expr>) -> FormaDoc<'a> {
+fn selection_doc<'a>(selection: Option<Expr>, options: &RenderOptions) -> FormaDoc<'a> {
     if let Some(selection) = selection {
-        RcDoc::line().append(RcDoc::text("where")).append(
-            RcDoc::line()
-                .nest(NEST_FACTOR)
-                .append(expr_doc(selection).nest(NEST_FACTOR).group()),
-        )
+        clause_line("where", options)
+            .append(keyword("where", options))
+            .append(
+                clause_separator(options).append(
+                    expr_doc(selection, options)
+                        .nest(options.clause_nest())
+                        .group(),
+                ),
+            )
     } else {
         RcDoc::nil()
     }
 }
 
-fn having_doc<'a>(having: Option<Expr>) -> FormaDoc<'a> {
-    if let Some(having) = having {
-        RcDoc::line()
-            .append(RcDoc::text("having").append(RcDoc::line().nest(NEST_FACTOR)))
-            .append(expr_doc(having))
+/// Transforms a clause filtering the groups or rows of a query, e.g. `having count(*) > 1`, into
+/// an `RcDoc`.
+fn condition_doc<'a>(
+    clause: &str,
+    condition: Option<Expr>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    if let Some(condition) = condition {
+        let condition = expr_doc(condition, options);
+        clause_line(clause, options)
+            .append(keyword(clause, options).append(clause_separator(options)))
+            .append(match options.layout {
+                Layout::Standard => condition,
+                Layout::River => condition.nest(options.clause_nest()).group(),
+            })
     } else {
         RcDoc::nil()
     }
 }
 
-fn exprs_doc<'a>(exprs: Exprs) -> FormaDoc<'a> {
+/// Separates Snowflake's `qualify` from the condition of the `having` clause, where it is marked,
+/// returning the conditions of both clauses.
+fn qualify(having: Option<Expr>, options: &RenderOptions) -> (Option<Expr>, Option<Expr>) {
+    match having {
+        Some(Expr::Function(Function { name, mut args, .. }))
+            if options.unmark_function(&name) == Some(Syntax::Qualify) =>
+        {
+            let qualify = args.pop();
+            (args.pop(), qualify)
+        }
+        having => (having, None),
+    }
+}
+
+fn exprs_doc<'a>(exprs: Exprs, options: &RenderOptions) -> FormaDoc<'a> {
     if !exprs.is_empty() {
-        parenthenized(interweave_comma(exprs.into_iter().map(expr_doc)))
+        parenthenized(
+            interweave_comma(
+                exprs.into_iter().map(|expr| expr_doc(expr, options)),
+                options,
+            ),
+            options,
+        )
     } else {
         RcDoc::nil()
     }
 }
 
-fn alias_doc<'a>(alias: Option<TableAlias>) -> FormaDoc<'a> {
-    if let Some(alias) = alias {
+fn alias_doc<'a>(alias: Option<TableAlias>, options: &RenderOptions) -> FormaDoc<'a> {
+    if let Some(TableAlias { name, columns }) = alias {
+        let (name, written) = options.unmark_alias(name);
         RcDoc::space()
-            .append(RcDoc::text("as").append(RcDoc::space()))
-            .append(RcDoc::text(alias.to_string()))
+            .append(as_doc(&name, written, options.table_alias, options))
+            .append(text(TableAlias { name, columns }.to_string(), options))
     } else {
         RcDoc::nil()
     }
 }
 
-fn relation_doc<'a>(relation: TableFactor) -> FormaDoc<'a> {
+fn relation_doc<'a>(relation: TableFactor, options: &RenderOptions) -> FormaDoc<'a> {
     match relation {
         TableFactor::Table {
             name,
             alias,
             args,
             with_hints,
-        } => RcDoc::text(name.to_string())
-            .append(exprs_doc(args))
-            .append(alias_doc(alias))
-            .append(if !with_hints.is_empty() {
-                RcDoc::space().append(RcDoc::text("with").append(RcDoc::space()).append(
-                    parenthenized(interweave_comma(with_hints.into_iter().map(expr_doc))),
-                ))
-            } else {
-                RcDoc::nil()
-            }),
+        } => match options.unmark_function(&name) {
+            // Snowflake's `lateral flatten(...)`.
+            Some(Syntax::Lateral) => keyword("lateral", options)
+                .append(RcDoc::concat(args.into_iter().map(|function| {
+                    RcDoc::space().append(expr_doc(function, options))
+                }))),
+            _ if args.is_empty() => text(name.to_string(), options),
+            // A table function, e.g. `unnest(a)`, is written like any other function.
+            _ => lowercase_ident(name.to_string(), options).append(exprs_doc(args, options)),
+        }
+        .append(alias_doc(alias, options))
+        .append(if let Some(alias) = with_offset(&with_hints, options) {
+            with_offset_doc(alias, options)
+        } else if !with_hints.is_empty() {
+            RcDoc::space().append(keyword("with", options).append(RcDoc::space()).append(
+                parenthenized(
+                    interweave_comma(
+                        with_hints.into_iter().map(|expr| expr_doc(expr, options)),
+                        options,
+                    ),
+                    options,
+                ),
+            ))
+        } else {
+            RcDoc::nil()
+        }),
         TableFactor::Derived {
             lateral,
             subquery,
             alias,
-        } => RcDoc::text(if lateral { "lateral " } else { "" })
-            .append(parenthenized(query_doc(*subquery)).append(alias_doc(alias))),
+        } => RcDoc::text(if lateral { "lateral " } else { "" }).append(
+            parenthenized(query_doc(*subquery, options), options).append(alias_doc(alias, options)),
+        ),
         TableFactor::NestedJoin(box TableWithJoins { relation, joins }) => {
-            relation_doc(relation).append(RcDoc::concat(joins.into_iter().map(join_doc)))
+            relation_doc(relation, options).append(RcDoc::concat(
+                joins.into_iter().map(|join| join_doc(join, options)),
+            ))
         }
     }
 }
 
-fn join_doc<'a>(join: Join) -> FormaDoc<'a> {
-    fn prefix<'a>(constraint: &JoinConstraint) -> FormaDoc<'a> {
+/// Returns the alias of BigQuery's `with offset` if the given table hints mark it, which is `None`
+/// where the offset has no alias.
+fn with_offset(with_hints: &[Expr], options: &RenderOptions) -> Option<Option<Ident>> {
+    match with_hints {
+        [Expr::Function(Function { name, args, .. })]
+            if options.unmark_function(name) == Some(Syntax::WithOffset) =>
+        {
+            match args.as_slice() {
+                [Expr::Identifier(alias)] => Some(Some(alias.clone())),
+                _ => Some(None),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Transforms BigQuery's `with offset` following an `unnest`, e.g. `with offset as o`, into an
+/// `RcDoc`.
+fn with_offset_doc<'a>(alias: Option<Ident>, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::space()
+        .append(keyword("with offset", options))
+        .append(match alias {
+            Some(alias) => {
+                let (alias, written) = options.unmark_alias(alias);
+                RcDoc::space()
+                    .append(as_doc(&alias, written, options.table_alias, options))
+                    .append(text(alias.to_string(), options))
+            }
+            None => RcDoc::nil(),
+        })
+}
+
+fn join_doc<'a>(join: Join, options: &RenderOptions) -> FormaDoc<'a> {
+    fn prefix<'a>(constraint: &JoinConstraint, options: &RenderOptions) -> FormaDoc<'a> {
         match constraint {
-            JoinConstraint::Natural => RcDoc::text("natural").append(RcDoc::space()),
+            JoinConstraint::Natural => keyword("natural", options).append(RcDoc::space()),
             _ => RcDoc::nil(),
         }
     }
 
-    fn suffix<'a>(constraint: &JoinConstraint) -> FormaDoc<'a> {
+    fn suffix<'a>(constraint: &JoinConstraint, options: &RenderOptions) -> FormaDoc<'a> {
         match constraint.clone() {
-            JoinConstraint::On(expr) => RcDoc::line()
-                .append(RcDoc::text("on").append(RcDoc::space().append(expr_doc(expr))))
-                .group(),
+            JoinConstraint::On(expr) => match options.layout {
+                Layout::Standard => RcDoc::line()
+                    .append(
+                        keyword("on", options)
+                            .append(RcDoc::space().append(expr_doc(expr, options))),
+                    )
+                    .group(),
+                // Right-align `on` with any `and` continuation lines, forming a river of its own.
+                Layout::River => RcDoc::line()
+                    .nest(1)
+                    .append(keyword("on", options).append(
+                        RcDoc::space().append(expr_doc(expr, options).nest(" and".len() as isize)),
+                    ))
+                    .group(),
+            },
             JoinConstraint::Using(attrs) => RcDoc::line()
                 .append(
-                    RcDoc::text("using")
+                    keyword("using", options)
                         .append(RcDoc::space())
-                        .append(parenthenized(interweave_comma(
-                            attrs.into_iter().map(ident_doc),
-                        ))),
+                        .append(parenthenized(
+                            interweave_comma(
+                                attrs.into_iter().map(|attr| ident_doc(attr, options)),
+                                options,
+                            ),
+                            options,
+                        )),
                 )
                 .group(),
             _ => RcDoc::nil(),
@@ -242,32 +415,39 @@ fn join_doc<'a>(join: Join) -> FormaDoc<'a> {
     }
 
     match join.join_operator {
-        JoinOperator::Inner(constraint) => prefix(&constraint).append(RcDoc::text("join").append(
-            RcDoc::space().append(relation_doc(join.relation).append(suffix(&constraint))),
-        )),
+        JoinOperator::Inner(constraint) => {
+            prefix(&constraint, options).append(keyword("join", options).append(
+                RcDoc::space().append(
+                    relation_doc(join.relation, options).append(suffix(&constraint, options)),
+                ),
+            ))
+        }
         JoinOperator::LeftOuter(constraint) => {
-            prefix(&constraint).append(RcDoc::text("left join").append(
-                RcDoc::space().append(relation_doc(join.relation).append(suffix(&constraint))),
+            prefix(&constraint, options).append(keyword("left join", options).append(
+                RcDoc::space().append(
+                    relation_doc(join.relation, options).append(suffix(&constraint, options)),
+                ),
             ))
         }
         JoinOperator::RightOuter(constraint) => {
-            prefix(&constraint).append(RcDoc::text("right join").append(
-                RcDoc::space().append(relation_doc(join.relation).append(suffix(&constraint))),
+            prefix(&constraint, options).append(keyword("right join", options).append(
+                RcDoc::space().append(
+                    relation_doc(join.relation, options).append(suffix(&constraint, options)),
+                ),
             ))
         }
         JoinOperator::FullOuter(constraint) => {
-            prefix(&constraint).append(RcDoc::text("full join").append(
-                RcDoc::space().append(relation_doc(join.relation).append(suffix(&constraint))),
+            prefix(&constraint, options).append(keyword("full join", options).append(
+                RcDoc::space().append(
+                    relation_doc(join.relation, options).append(suffix(&constraint, options)),
+                ),
             ))
         }
-        JoinOperator::CrossJoin => {
-            RcDoc::text("cross join").append(RcDoc::space().append(relation_doc(join.relation)))
-        }
-        JoinOperator::CrossApply => {
-            RcDoc::text("cross apply").append(RcDoc::space().append(relation_doc(join.relation)))
-        }
-        JoinOperator::OuterApply => {
-            RcDoc::text("outer apply").append(RcDoc::space().append(relation_doc(join.relation)))
-        }
+        JoinOperator::CrossJoin => keyword("cross join", options)
+            .append(RcDoc::space().append(relation_doc(join.relation, options))),
+        JoinOperator::CrossApply => keyword("cross apply", options)
+            .append(RcDoc::space().append(relation_doc(join.relation, options))),
+        JoinOperator::OuterApply => keyword("outer apply", options)
+            .append(RcDoc::space().append(relation_doc(join.relation, options))),
     }
 }
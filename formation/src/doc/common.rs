@@ -2,9 +2,12 @@ use std::fmt;
 
 use pretty::RcDoc;
 use sqlparser::ast::{Expr, Ident, OrderByExpr};
+use sqlparser::dialect::keywords::ALL_KEYWORDS;
 
-use crate::constants::NEST_FACTOR;
+use crate::constants::RIVER_WIDTH;
 use crate::doc::expr::expr_doc;
+use crate::doc::RenderOptions;
+use crate::options::{AliasStyle, CommaPosition, Layout};
 
 pub type FormaDoc<'a> = RcDoc<'a, ()>;
 
@@ -32,52 +35,157 @@ pub fn escape_single_quote_string(s: &str) -> EscapeSingleQuoteString<'_> {
 }
 
 /// Interweaves the provides documents with a comma.
-pub fn interweave_comma<'a, D>(docs: D) -> FormaDoc<'a>
+///
+/// When the list breaks, the comma either ends each line or leads the next, in accordance with
+/// the configured `CommaPosition`.
+pub fn interweave_comma<'a, D>(docs: D, options: &RenderOptions) -> FormaDoc<'a>
 where
     D: Iterator<Item = FormaDoc<'a>>,
 {
-    RcDoc::intersperse(docs, RcDoc::text(",").append(RcDoc::line()))
+    match options.comma_position {
+        CommaPosition::Trailing => RcDoc::intersperse(docs, RcDoc::text(",").append(RcDoc::line())),
+        CommaPosition::Leading => {
+            RcDoc::intersperse(docs, RcDoc::line_().append(RcDoc::text(", ")))
+        }
+    }
+}
+
+/// Writes the given keyword in the configured keyword case.
+pub fn keyword<'a>(keyword: &str, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::text(options.keyword_case.apply(keyword))
+}
+
+/// Writes the given text, e.g. an identifier or a value, restoring any syntax masked from the
+/// parser within it.
+pub fn text<'a>(text: String, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::text(options.unmask(&text))
+}
+
+/// Writes the given identifier, lowercasing it and restoring any syntax masked from the parser
+/// within it.
+pub fn lowercase_ident<'a>(ident: String, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::text(options.unmask_identifier(&ident.to_lowercase()))
+}
+
+/// Breaks the line ahead of the given clause keyword.
+///
+/// In the river layout the keyword is right-aligned, i.e. padded such that its first word ends in
+/// the same column as `select`.
+pub fn clause_line<'a>(keyword: &str, options: &RenderOptions) -> FormaDoc<'a> {
+    match options.layout {
+        Layout::Standard => RcDoc::line(),
+        Layout::River => {
+            let first_word = keyword.split(' ').next().unwrap_or(keyword);
+            RcDoc::line().nest(RIVER_WIDTH.saturating_sub(first_word.len()) as isize)
+        }
+    }
+}
+
+/// Separates a clause keyword from the clause body.
+pub fn clause_separator<'a>(options: &RenderOptions) -> FormaDoc<'a> {
+    match options.layout {
+        Layout::Standard => RcDoc::line().nest(options.nest_factor()),
+        Layout::River => RcDoc::space(),
+    }
+}
+
+/// Breaks the line such that what follows hangs `hang` columns to the left of the current
+/// indentation, without moving past the start of the line.
+pub fn hanging_line<'a>(hang: usize) -> FormaDoc<'a> {
+    RcDoc::nesting(move |indent| RcDoc::line().nest(-(hang.min(indent) as isize)))
 }
 
 /// Surrounds the provided document with parenthesis.
-pub fn parenthenized(doc: FormaDoc<'_>) -> FormaDoc<'_> {
+pub fn parenthenized<'a>(doc: FormaDoc<'a>, options: &RenderOptions) -> FormaDoc<'a> {
     RcDoc::text("(")
         .append(RcDoc::line_())
         .append(doc)
-        .nest(NEST_FACTOR)
+        .nest(options.nest_factor())
         .append(RcDoc::line_())
         .append(RcDoc::text(")"))
         .group()
 }
 
+/// Encloses the given doc in square brackets, e.g. the elements of an array.
+pub fn bracketed<'a>(doc: FormaDoc<'a>, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::text("[")
+        .append(RcDoc::line_())
+        .append(doc)
+        .nest(options.nest_factor())
+        .append(RcDoc::line_())
+        .append(RcDoc::text("]"))
+        .group()
+}
+
 pub fn order_by_doc<'a>(
     OrderByExpr {
         expr,
         asc,
         nulls_first,
     }: OrderByExpr,
+    options: &RenderOptions,
 ) -> FormaDoc<'a> {
-    expr_doc(expr)
+    expr_doc(expr, options)
         .append(if let Some(asc) = asc {
             RcDoc::line().append(if asc {
-                RcDoc::text("asc")
+                keyword("asc", options)
             } else {
-                RcDoc::text("desc")
+                keyword("desc", options)
             })
         } else {
             RcDoc::nil()
         })
         .append(if let Some(nulls_first) = nulls_first {
             RcDoc::line().append(if nulls_first {
-                RcDoc::text("nulls first")
+                keyword("nulls first", options)
             } else {
-                RcDoc::text("nulls last")
+                keyword("nulls last", options)
             })
         } else {
             RcDoc::nil()
         })
 }
 
-pub fn ident_doc<'a>(ident: Ident) -> FormaDoc<'a> {
-    RcDoc::text(format!("{}", ident).to_lowercase())
+/// Writes the `as` keyword ahead of the given alias, as the alias style dictates. `written`
+/// gives whether the source SQL introduced the alias with `as`.
+///
+/// `as` is never left out where that could change how the SQL parses, i.e. before an unquoted
+/// alias which is a keyword or before a single-quoted alias.
+pub fn as_doc<'a>(
+    alias: &Ident,
+    written: bool,
+    style: AliasStyle,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    let explicit = match style {
+        AliasStyle::Explicit => true,
+        AliasStyle::Implicit => false,
+        AliasStyle::Preserve => written,
+    };
+    if explicit || is_ambiguous_alias(alias, options) {
+        keyword("as", options).append(RcDoc::space())
+    } else {
+        RcDoc::nil()
+    }
+}
+
+/// Returns `true` if the alias cannot be written without `as`, being a keyword of sqlparser or a
+/// word the dialect reserves.
+fn is_ambiguous_alias(alias: &Ident, options: &RenderOptions) -> bool {
+    match alias.quote_style {
+        Some(quote) => quote == '\'',
+        None => {
+            let word = alias.value.to_uppercase();
+            ALL_KEYWORDS.contains(&word.as_str())
+                || options.profile().reserved_words.contains(&word.as_str())
+        }
+    }
+}
+
+pub fn ident_doc<'a>(ident: Ident, options: &RenderOptions) -> FormaDoc<'a> {
+    if ident.quote_style.is_some() && options.profile().preserve_quoted_case {
+        text(ident.to_string(), options)
+    } else {
+        lowercase_ident(ident.to_string(), options)
+    }
 }
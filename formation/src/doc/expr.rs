@@ -4,97 +4,149 @@ use sqlparser::ast::{
     ObjectName, Query, UnaryOperator, Value, WindowFrame, WindowSpec,
 };
 
-use crate::constants::NEST_FACTOR;
 use crate::doc::common::{
-    escape_single_quote_string, ident_doc, interweave_comma, order_by_doc, parenthenized, Exprs,
-    FormaDoc, Idents,
+    bracketed, escape_single_quote_string, hanging_line, ident_doc, interweave_comma, keyword,
+    lowercase_ident, order_by_doc, parenthenized, text, Exprs, FormaDoc, Idents,
 };
 use crate::doc::query::query_doc;
+use crate::doc::RenderOptions;
+use crate::options::Layout;
+use crate::scan::{CastStyle, Syntax};
 
 /// Returns a doc from the given `Expr`.
-pub fn expr_doc<'a>(expr: Expr) -> FormaDoc<'a> {
+pub fn expr_doc<'a>(expr: Expr, options: &RenderOptions) -> FormaDoc<'a> {
     match expr {
         Expr::Between {
             expr,
             negated,
             low,
             high,
-        } => between_doc(*expr, negated, *low, *high),
-        Expr::BinaryOp { left, op, right } => binary_op_doc(op, *left, *right),
+        } => between_doc(*expr, negated, *low, *high, options),
+        Expr::BinaryOp { left, op, right } => binary_op_doc(op, *left, *right, options),
         Expr::Case {
             operand,
             conditions,
             results,
             else_result,
-        } => case_doc(operand, conditions, results, else_result),
-        Expr::Cast { expr, data_type } => cast_doc(*expr, data_type),
-        Expr::Collate { expr, collation } => collate_doc(*expr, collation),
-        Expr::CompoundIdentifier(idents) => compound_identifier_doc(idents),
-        Expr::Exists(box query) => exists_doc(query),
-        Expr::Extract { field, expr } => extract_doc(field, *expr),
+        } => case_doc(operand, conditions, results, else_result, options),
+        Expr::Cast { expr, data_type } => cast_doc(*expr, data_type, options),
+        Expr::Collate { expr, collation } => collate_doc(*expr, collation, options),
+        Expr::CompoundIdentifier(idents) => compound_identifier_doc(idents, options),
+        Expr::Exists(box query) => exists_doc(query, options),
+        Expr::Extract { field, expr } => extract_doc(field, *expr, options),
         Expr::Function(Function {
             name,
             args,
             over,
             distinct,
-        }) => function_doc(name, args, over, distinct),
-        Expr::Identifier(ident) => ident_doc(ident),
+        }) => function_doc(name, args, over, distinct, options),
+        Expr::Identifier(ident) => ident_doc(ident, options),
         Expr::InList {
             expr,
             negated,
             list,
-        } => in_list_doc(*expr, negated, list),
+        } => in_list_doc(*expr, negated, list, options),
         Expr::InSubquery {
             expr,
             negated,
             subquery,
-        } => in_subquery_doc(*expr, negated, *subquery),
-        Expr::IsNotNull(expr) => is_not_null_doc(*expr),
-        Expr::IsNull(expr) => is_null_doc(*expr),
-        Expr::ListAgg(listagg) => listagg_doc(listagg),
-        Expr::Nested(expr) => nested_doc(*expr),
-        Expr::QualifiedWildcard(idents) => qualified_wildcard_doc(idents),
-        Expr::Subquery(box query) => subquery_doc(query),
-        Expr::TypedString { data_type, value } => typed_string_doc(data_type, value),
-        Expr::UnaryOp { expr, op } => unary_op_doc(op, *expr),
-        Expr::Value(value) => value_doc(value),
+        } => in_subquery_doc(*expr, negated, *subquery, options),
+        Expr::IsNotNull(expr) => is_not_null_doc(*expr, options),
+        Expr::IsNull(expr) => is_null_doc(*expr, options),
+        Expr::ListAgg(listagg) => listagg_doc(listagg, options),
+        Expr::Nested(expr) => nested_doc(*expr, options),
+        Expr::QualifiedWildcard(idents) => qualified_wildcard_doc(idents, options),
+        Expr::Subquery(box query) => subquery_doc(query, options),
+        Expr::TypedString { data_type, value } => typed_string_doc(data_type, value, options),
+        Expr::UnaryOp { expr, op } => unary_op_doc(op, *expr, options),
+        Expr::Value(value) => value_doc(value, options),
         Expr::Wildcard => RcDoc::text("*"),
     }
 }
 
-fn between_doc<'a>(expr: Expr, negated: bool, low: Expr, high: Expr) -> FormaDoc<'a> {
-    expr_doc(expr)
+fn between_doc<'a>(
+    expr: Expr,
+    negated: bool,
+    low: Expr,
+    high: Expr,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    expr_doc(expr, options)
         .append(if negated {
-            RcDoc::text("not")
+            keyword("not", options)
         } else {
             RcDoc::nil()
         })
         .append(
             RcDoc::space().append(
-                RcDoc::text("between")
+                keyword("between", options)
                     .append(RcDoc::space())
-                    .append(expr_doc(low))
+                    .append(expr_doc(low, options))
                     .append(
                         RcDoc::space()
-                            .append(RcDoc::text("and"))
+                            .append(keyword("and", options))
                             .append(RcDoc::space()),
                     )
-                    .append(expr_doc(high)),
+                    .append(expr_doc(high, options)),
             ),
         )
 }
 
-fn binary_op_doc<'a>(op: BinaryOperator, left: Expr, right: Expr) -> FormaDoc<'a> {
-    let op_string = op.to_string().to_lowercase();
-    expr_doc(left)
+fn binary_op_doc<'a>(
+    op: BinaryOperator,
+    left: Expr,
+    right: Expr,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    // An operator the parser does not understand is marked as `left | mark | right`.
+    if op == BinaryOperator::BitwiseOr {
+        if let Expr::BinaryOp {
+            left: operand,
+            op: BinaryOperator::BitwiseOr,
+            right: mark,
+        } = &left
+        {
+            if let Some(operator) = options.unmark_operator(mark) {
+                return marked_operator_doc(operator, (**operand).clone(), right, options);
+            }
+        }
+    }
+    let op_string = op.to_string();
+    expr_doc(left, options)
         .append(if is_newline_op(&op) {
-            RcDoc::line()
-                .append(RcDoc::text(op_string))
-                .append(RcDoc::space())
+            match options.layout {
+                Layout::Standard => RcDoc::line(),
+                // Hang the operator to the left of the river.
+                Layout::River => hanging_line(op_string.len() + 1),
+            }
+            .append(keyword(&op_string, options))
+            .append(RcDoc::space())
         } else {
-            RcDoc::space().append(RcDoc::text(op_string).append(RcDoc::space()))
+            RcDoc::space().append(keyword(&op_string, options).append(RcDoc::space()))
         })
-        .append(expr_doc(right))
+        .append(expr_doc(right, options))
+}
+
+/// Transforms an operator the parser does not understand, e.g. PostgreSQL's `->>` or `ilike`, into
+/// an `RcDoc`.
+///
+/// The JSON path operators, e.g. `data->>'name'`, are written without spaces, as they read as part
+/// of the operand they select from.
+fn marked_operator_doc<'a>(
+    operator: &str,
+    left: Expr,
+    right: Expr,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    let operator_doc = match operator {
+        "->" | "->>" | "#>" | "#>>" => RcDoc::text(operator.to_owned()),
+        _ => RcDoc::space()
+            .append(keyword(operator, options))
+            .append(RcDoc::space()),
+    };
+    expr_doc(left, options)
+        .append(operator_doc)
+        .append(expr_doc(right, options))
 }
 
 fn case_doc<'a>(
@@ -102,79 +154,115 @@ fn case_doc<'a>(
     conditions: Exprs,
     results: Exprs,
     else_result: Option<Box<Expr>>,
+    options: &RenderOptions,
 ) -> FormaDoc<'a> {
-    RcDoc::text("case")
+    keyword("case", options)
         .append(if let Some(operand) = operand {
-            RcDoc::space().append(expr_doc(*operand))
+            RcDoc::space().append(expr_doc(*operand, options))
         } else {
             RcDoc::nil()
         })
         .append(
-            RcDoc::line().nest(NEST_FACTOR).append(
+            RcDoc::line().nest(options.nest_factor()).append(
                 RcDoc::intersperse(
                     conditions.iter().zip(results).map(|(condition, result)| {
-                        RcDoc::text("when")
+                        keyword("when", options)
                             .append(RcDoc::space())
-                            .append(expr_doc(condition.clone()))
+                            .append(expr_doc(condition.clone(), options))
                             .append(RcDoc::space())
-                            .append(RcDoc::text("then"))
+                            .append(keyword("then", options))
                             .append(RcDoc::space())
-                            .append(expr_doc(result))
+                            .append(expr_doc(result, options))
                     }),
                     RcDoc::line(),
                 )
                 .append(if let Some(else_result) = else_result {
-                    RcDoc::line().nest(NEST_FACTOR).append(
-                        RcDoc::text("else")
+                    RcDoc::line().nest(options.nest_factor()).append(
+                        keyword("else", options)
                             .append(RcDoc::space())
-                            .append(expr_doc(*else_result)),
+                            .append(expr_doc(*else_result, options)),
                     )
                 } else {
                     RcDoc::nil()
                 }),
             ),
         )
-        .append(RcDoc::line().append(RcDoc::text("end")))
+        .append(RcDoc::line().append(keyword("end", options)))
+}
+
+fn cast_doc<'a>(expr: Expr, data_type: DataType, options: &RenderOptions) -> FormaDoc<'a> {
+    // `::` binds tighter than any operator, so it may only follow an operand that needs no
+    // parentheses of its own.
+    let (data_type, style) = options.unmark_cast(data_type);
+    if style == CastStyle::DoubleColon && options.profile().preserve_cast_style && is_atomic(&expr)
+    {
+        return expr_doc(expr, options)
+            .append(RcDoc::text("::"))
+            .append(keyword(&data_type.to_string(), options));
+    }
+    cast_function_doc("cast", expr, data_type, options)
 }
 
-fn cast_doc<'a>(expr: Expr, data_type: DataType) -> FormaDoc<'a> {
-    RcDoc::text("cast")
+/// Transforms a cast written as a function, e.g. `cast(a as int)`, into an `RcDoc`.
+fn cast_function_doc<'a>(
+    function: &str,
+    expr: Expr,
+    data_type: DataType,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    keyword(function, options)
         .append(RcDoc::text("("))
         .append(
-            expr_doc(expr)
+            expr_doc(expr, options)
                 .append(RcDoc::space())
-                .append(RcDoc::text("as"))
+                .append(keyword("as", options))
                 .append(RcDoc::space())
-                .append(RcDoc::text(data_type.to_string().to_lowercase())),
+                .append(keyword(&data_type.to_string(), options)),
         )
         .append(RcDoc::text(")"))
 }
 
-fn collate_doc<'a>(expr: Expr, collation: ObjectName) -> FormaDoc<'a> {
-    expr_doc(expr)
+fn is_atomic(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Cast { .. }
+            | Expr::CompoundIdentifier(_)
+            | Expr::Function(_)
+            | Expr::Identifier(_)
+            | Expr::Nested(_)
+            | Expr::Value(_)
+    )
+}
+
+fn collate_doc<'a>(expr: Expr, collation: ObjectName, options: &RenderOptions) -> FormaDoc<'a> {
+    expr_doc(expr, options)
         .append(RcDoc::space())
-        .append(RcDoc::text("collate"))
+        .append(keyword("collate", options))
         .append(RcDoc::space())
-        .append(RcDoc::text(collation.to_string()))
+        .append(text(collation.to_string(), options))
 }
 
-fn compound_identifier_doc<'a>(idents: Idents) -> FormaDoc<'a> {
-    RcDoc::intersperse(idents.into_iter().map(ident_doc), RcDoc::text("."))
+fn compound_identifier_doc<'a>(idents: Idents, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::intersperse(
+        idents.into_iter().map(|ident| ident_doc(ident, options)),
+        RcDoc::text("."),
+    )
 }
 
-fn exists_doc<'a>(query: Query) -> FormaDoc<'a> {
-    RcDoc::text("exists").append(RcDoc::softline().append(parenthenized(query_doc(query))))
+fn exists_doc<'a>(query: Query, options: &RenderOptions) -> FormaDoc<'a> {
+    keyword("exists", options)
+        .append(RcDoc::softline().append(parenthenized(query_doc(query, options), options)))
 }
 
-fn extract_doc<'a>(field: DateTimeField, expr: Expr) -> FormaDoc<'a> {
-    RcDoc::text("extract")
+fn extract_doc<'a>(field: DateTimeField, expr: Expr, options: &RenderOptions) -> FormaDoc<'a> {
+    keyword("extract", options)
         .append(RcDoc::text("("))
         .append(
-            RcDoc::text(field.to_string().to_lowercase())
+            keyword(&field.to_string(), options)
                 .append(RcDoc::space())
-                .append(RcDoc::text("from"))
+                .append(keyword("from", options))
                 .append(RcDoc::space())
-                .append(expr_doc(expr)),
+                .append(expr_doc(expr, options)),
         )
         .append(RcDoc::text(")"))
 }
@@ -184,20 +272,107 @@ fn function_doc<'a>(
     args: Exprs,
     over: Option<WindowSpec>,
     distinct: bool,
+    options: &RenderOptions,
 ) -> FormaDoc<'a> {
-    RcDoc::text(name.to_string().to_lowercase())
+    match (options.unmark_function(&name), args.as_slice()) {
+        // `array[...]`.
+        (Some(Syntax::Array), _) => {
+            return keyword("array", options).append(bracketed(
+                interweave_comma(
+                    args.into_iter().map(|expr| expr_doc(expr, options)),
+                    options,
+                ),
+                options,
+            ))
+        }
+        // `array(select ...)`.
+        (Some(Syntax::ArraySubquery), [Expr::Subquery(query)]) => {
+            return keyword("array", options).append(parenthenized(
+                query_doc((**query).clone(), options),
+                options,
+            ))
+        }
+        // BigQuery's `safe_cast(a as int64)`.
+        (Some(Syntax::SafeCast), [Expr::Cast { expr, data_type }]) => {
+            let (data_type, _) = options.unmark_cast(data_type.clone());
+            return cast_function_doc("safe_cast", (**expr).clone(), data_type, options);
+        }
+        // BigQuery's `* except (a, b)`.
+        (Some(Syntax::Except), [wildcard, columns @ ..]) => {
+            return expr_doc(wildcard.clone(), options)
+                .append(RcDoc::space())
+                .append(keyword("except", options))
+                .append(RcDoc::space())
+                .append(parenthenized(
+                    interweave_comma(
+                        columns
+                            .iter()
+                            .map(|column| expr_doc(column.clone(), options)),
+                        options,
+                    ),
+                    options,
+                ))
+        }
+        // BigQuery's `* replace (a + 1 as a)`.
+        (Some(Syntax::Replace), [wildcard, replacements @ ..]) => {
+            return expr_doc(wildcard.clone(), options)
+                .append(RcDoc::space())
+                .append(keyword("replace", options))
+                .append(RcDoc::space())
+                .append(parenthenized(
+                    interweave_comma(
+                        replacements.chunks(2).map(|replacement| match replacement {
+                            [expr, Expr::Identifier(name)] => expr_doc(expr.clone(), options)
+                                .append(RcDoc::space())
+                                .append(keyword("as", options))
+                                .append(RcDoc::space())
+                                .append(ident_doc(options.unmark_alias(name.clone()).0, options)),
+                            _ => interweave_comma(
+                                replacement
+                                    .iter()
+                                    .map(|expr| expr_doc(expr.clone(), options)),
+                                options,
+                            ),
+                        }),
+                        options,
+                    ),
+                    options,
+                ))
+        }
+        _ => {}
+    }
+    // BigQuery's value constructed with its type, e.g. `struct<a int64>(1)`, or an array's with
+    // brackets, e.g. `array<int64>[1, 2]`.
+    if let Some(data_type) = options.unmark_type(&name) {
+        let values = interweave_comma(
+            args.into_iter().map(|expr| expr_doc(expr, options)),
+            options,
+        );
+        return keyword(data_type, options).append(
+            if data_type.to_ascii_lowercase().starts_with("array") {
+                bracketed(values, options)
+            } else {
+                parenthenized(values, options)
+            },
+        );
+    }
+    lowercase_ident(name.to_string(), options)
         .append(parenthenized(
             if distinct {
-                RcDoc::text("distinct").append(RcDoc::space())
+                keyword("distinct", options).append(RcDoc::space())
             } else {
                 RcDoc::nil()
             }
-            .append(interweave_comma(args.into_iter().map(expr_doc))),
+            .append(interweave_comma(
+                args.into_iter().map(|expr| expr_doc(expr, options)),
+                options,
+            )),
+            options,
         ))
-        .append(window_spec_doc(over))
+        .append(window_spec_doc(over, options))
 }
 
-fn window_spec_doc<'a>(window_spec: Option<WindowSpec>) -> FormaDoc<'a> {
+fn window_spec_doc<'a>(window_spec: Option<WindowSpec>, options: &RenderOptions) -> FormaDoc<'a> {
     if let Some(WindowSpec {
         partition_by,
         order_by,
@@ -205,23 +380,32 @@ fn window_spec_doc<'a>(window_spec: Option<WindowSpec>) -> FormaDoc<'a> {
     }) = window_spec
     {
         RcDoc::space().append(
-            RcDoc::text("over").append(parenthenized(
+            keyword("over", options).append(parenthenized(
                 if !partition_by.is_empty() {
-                    RcDoc::text("partition by")
+                    keyword("partition by", options)
                         .append(RcDoc::space())
-                        .append(interweave_comma(partition_by.into_iter().map(expr_doc)))
+                        .append(interweave_comma(
+                            partition_by.into_iter().map(|expr| expr_doc(expr, options)),
+                            options,
+                        ))
                         .append(RcDoc::space())
                 } else {
                     RcDoc::nil()
                 }
                 .append(if !order_by.is_empty() {
-                    RcDoc::text("order by")
+                    keyword("order by", options)
                         .append(RcDoc::space())
-                        .append(interweave_comma(order_by.into_iter().map(order_by_doc)))
+                        .append(interweave_comma(
+                            order_by
+                                .into_iter()
+                                .map(|order_by| order_by_doc(order_by, options)),
+                            options,
+                        ))
                 } else {
                     RcDoc::nil()
                 })
-                .append(window_frame_doc(window_frame)),
+                .append(window_frame_doc(window_frame, options)),
+                options,
             )),
         )
     } else {
@@ -229,7 +413,10 @@ fn window_spec_doc<'a>(window_spec: Option<WindowSpec>) -> FormaDoc<'a> {
     }
 }
 
-fn window_frame_doc<'a>(window_frame: Option<WindowFrame>) -> FormaDoc<'a> {
+fn window_frame_doc<'a>(
+    window_frame: Option<WindowFrame>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
     if let Some(WindowFrame {
         units,
         start_bound,
@@ -237,16 +424,16 @@ fn window_frame_doc<'a>(window_frame: Option<WindowFrame>) -> FormaDoc<'a> {
     }) = window_frame
     {
         RcDoc::line_().append(
-            RcDoc::text(units.to_string().to_lowercase())
+            keyword(&units.to_string(), options)
                 .append(RcDoc::space())
-                .append(RcDoc::text("between"))
+                .append(keyword("between", options))
                 .append(RcDoc::space())
-                .append(start_bound.to_string().to_lowercase())
+                .append(keyword(&start_bound.to_string(), options))
                 .append(if let Some(end_bound) = end_bound {
                     RcDoc::space()
-                        .append(RcDoc::text("and"))
+                        .append(keyword("and", options))
                         .append(RcDoc::space())
-                        .append(RcDoc::text(end_bound.to_string().to_lowercase()))
+                        .append(keyword(&end_bound.to_string(), options))
                 } else {
                     RcDoc::nil()
                 }),
@@ -256,26 +443,41 @@ fn window_frame_doc<'a>(window_frame: Option<WindowFrame>) -> FormaDoc<'a> {
     }
 }
 
-fn in_list_doc<'a>(expr: Expr, negated: bool, list: Exprs) -> FormaDoc<'a> {
-    resolve_negation(expr, negated).append(parenthenized(interweave_comma(
-        list.into_iter().map(expr_doc),
-    )))
+fn in_list_doc<'a>(
+    expr: Expr,
+    negated: bool,
+    list: Exprs,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    resolve_negation(expr, negated, options).append(parenthenized(
+        interweave_comma(
+            list.into_iter().map(|expr| expr_doc(expr, options)),
+            options,
+        ),
+        options,
+    ))
 }
 
-fn is_not_null_doc<'a>(expr: Expr) -> FormaDoc<'a> {
-    expr_doc(expr)
+fn is_not_null_doc<'a>(expr: Expr, options: &RenderOptions) -> FormaDoc<'a> {
+    expr_doc(expr, options)
         .append(RcDoc::space())
-        .append(RcDoc::text("is not null"))
+        .append(keyword("is not null", options))
 }
 
-fn is_null_doc<'a>(expr: Expr) -> FormaDoc<'a> {
-    expr_doc(expr)
+fn is_null_doc<'a>(expr: Expr, options: &RenderOptions) -> FormaDoc<'a> {
+    expr_doc(expr, options)
         .append(RcDoc::space())
-        .append(RcDoc::text("is null"))
+        .append(keyword("is null", options))
 }
 
-fn in_subquery_doc<'a>(expr: Expr, negated: bool, subquery: Query) -> FormaDoc<'a> {
-    resolve_negation(expr, negated).append(parenthenized(query_doc(subquery)))
+fn in_subquery_doc<'a>(
+    expr: Expr,
+    negated: bool,
+    subquery: Query,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    resolve_negation(expr, negated, options)
+        .append(parenthenized(query_doc(subquery, options), options))
 }
 
 fn listagg_doc<'a>(
@@ -286,30 +488,37 @@ fn listagg_doc<'a>(
         on_overflow,
         within_group,
     }: ListAgg,
+    options: &RenderOptions,
 ) -> FormaDoc<'a> {
-    RcDoc::text("listagg")
+    keyword("listagg", options)
         .append(parenthenized(
             if distinct {
-                RcDoc::text("distinct").append(RcDoc::space())
+                keyword("distinct", options).append(RcDoc::space())
             } else {
                 RcDoc::nil()
             }
-            .append(expr_doc(*expr))
+            .append(expr_doc(*expr, options))
             .append(if let Some(separator) = separator {
-                RcDoc::text(", ").append(expr_doc(*separator))
+                RcDoc::text(", ").append(expr_doc(*separator, options))
             } else {
                 RcDoc::nil()
             })
             .append(if let Some(on_overflow) = on_overflow {
-                listagg_on_overflow_doc(on_overflow)
+                listagg_on_overflow_doc(on_overflow, options)
             } else {
                 RcDoc::nil()
             }),
+            options,
         ))
         .append(if !within_group.is_empty() {
             RcDoc::line().append(
                 RcDoc::text("within group (order by ")
-                    .append(interweave_comma(within_group.into_iter().map(order_by_doc)))
+                    .append(interweave_comma(
+                        within_group
+                            .into_iter()
+                            .map(|order_by| order_by_doc(order_by, options)),
+                        options,
+                    ))
                     .append(RcDoc::text(")")),
             )
         } else {
@@ -317,75 +526,92 @@ fn listagg_doc<'a>(
         })
 }
 
-fn listagg_on_overflow_doc<'a>(on_overflow: ListAggOnOverflow) -> FormaDoc<'a> {
-    RcDoc::text(" on overflow").append(match on_overflow {
-        ListAggOnOverflow::Error => RcDoc::text(" error"),
-        ListAggOnOverflow::Truncate { filler, with_count } => RcDoc::text(" truncate")
+fn listagg_on_overflow_doc<'a>(
+    on_overflow: ListAggOnOverflow,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    keyword(" on overflow", options).append(match on_overflow {
+        ListAggOnOverflow::Error => keyword(" error", options),
+        ListAggOnOverflow::Truncate { filler, with_count } => keyword(" truncate", options)
             .append(if let Some(filler) = filler {
-                RcDoc::space().append(expr_doc(*filler))
+                RcDoc::space().append(expr_doc(*filler, options))
             } else {
                 RcDoc::nil()
             })
             .append(if with_count {
-                RcDoc::text(" with count")
+                keyword(" with count", options)
             } else {
-                RcDoc::text(" without count")
+                keyword(" without count", options)
             }),
     })
 }
 
-fn nested_doc<'a>(expr: Expr) -> FormaDoc<'a> {
+fn nested_doc<'a>(expr: Expr, options: &RenderOptions) -> FormaDoc<'a> {
     RcDoc::text("(")
         .append(RcDoc::softline_())
-        .append(expr_doc(expr).group())
-        .nest(NEST_FACTOR)
+        .append(expr_doc(expr, options).group())
+        .nest(options.nest_factor())
         .append(RcDoc::softline_())
         .append(RcDoc::text(")"))
 }
 
-fn qualified_wildcard_doc<'a>(idents: Idents) -> FormaDoc<'a> {
-    RcDoc::intersperse(idents.into_iter().map(ident_doc), RcDoc::text("."))
-        .append(RcDoc::text(".*"))
+fn qualified_wildcard_doc<'a>(idents: Idents, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::intersperse(
+        idents.into_iter().map(|ident| ident_doc(ident, options)),
+        RcDoc::text("."),
+    )
+    .append(RcDoc::text(".*"))
 }
 
-fn subquery_doc<'a>(query: Query) -> FormaDoc<'a> {
-    RcDoc::softline_().append(parenthenized(query_doc(query)))
+fn subquery_doc<'a>(query: Query, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::softline_().append(parenthenized(query_doc(query, options), options))
 }
 
-fn typed_string_doc<'a>(data_type: DataType, value: String) -> FormaDoc<'a> {
-    RcDoc::text(data_type.to_string().to_lowercase())
+fn typed_string_doc<'a>(
+    data_type: DataType,
+    value: String,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    keyword(&data_type.to_string(), options)
         .append(RcDoc::space())
-        .append(RcDoc::text(format!("'{}'", value)))
+        .append(text(format!("'{}'", value), options))
 }
 
-fn unary_op_doc<'a>(op: UnaryOperator, expr: Expr) -> FormaDoc<'a> {
-    RcDoc::text(op.to_string().to_lowercase()).append(expr_doc(expr))
+fn unary_op_doc<'a>(op: UnaryOperator, expr: Expr, options: &RenderOptions) -> FormaDoc<'a> {
+    keyword(&op.to_string(), options).append(expr_doc(expr, options))
 }
 
-fn value_doc<'a>(value: Value) -> FormaDoc<'a> {
+pub fn value_doc<'a>(value: Value, options: &RenderOptions) -> FormaDoc<'a> {
+    let case = |keyword: &str| options.keyword_case.apply(keyword);
     match value {
-        Value::Null => RcDoc::text("null"),
+        Value::Null => keyword("null", options),
+        Value::Boolean(_) => keyword(&value.to_string(), options),
         Value::Interval {
             value,
             leading_field: Some(DateTimeField::Second),
             leading_precision: Some(leading_precision),
             last_field: _,
             fractional_seconds_precision: Some(fractional_seconds_precision),
-        } => RcDoc::text(format!(
-            "interval '{}' second ({}, {})",
-            escape_single_quote_string(&value),
-            leading_precision,
-            fractional_seconds_precision
-        )),
+        } => text(
+            format!(
+                "{} '{}' {} ({}, {})",
+                case("interval"),
+                escape_single_quote_string(&value),
+                case("second"),
+                leading_precision,
+                fractional_seconds_precision
+            ),
+            options,
+        ),
         Value::Interval {
             value,
             leading_field,
             leading_precision,
             last_field,
             fractional_seconds_precision,
-        } => RcDoc::text(format!("interval '{}'", value))
+        } => text(format!("{} '{}'", case("interval"), value), options)
             .append(if let Some(leading_field) = leading_field {
-                RcDoc::text(format!(" {}", leading_field).to_lowercase())
+                RcDoc::text(format!(" {}", case(&leading_field.to_string())))
             } else {
                 RcDoc::nil()
             })
@@ -395,7 +621,7 @@ fn value_doc<'a>(value: Value) -> FormaDoc<'a> {
                 RcDoc::nil()
             })
             .append(if let Some(last_field) = last_field {
-                RcDoc::text(format!(" to {}", last_field).to_lowercase())
+                RcDoc::text(case(&format!(" to {}", last_field)))
             } else {
                 RcDoc::nil()
             })
@@ -406,20 +632,20 @@ fn value_doc<'a>(value: Value) -> FormaDoc<'a> {
                     RcDoc::nil()
                 },
             ),
-        _ => RcDoc::text(value.to_string()),
+        _ => text(value.to_string(), options),
     }
 }
 
 /// Resolves a possibly negated expression to an `RcDoc`.
-pub fn resolve_negation<'a>(expr: Expr, negated: bool) -> FormaDoc<'a> {
-    expr_doc(expr)
+pub fn resolve_negation<'a>(expr: Expr, negated: bool, options: &RenderOptions) -> FormaDoc<'a> {
+    expr_doc(expr, options)
         .append(RcDoc::space())
         .append(if negated {
-            RcDoc::text("not").append(RcDoc::space())
+            keyword("not", options).append(RcDoc::space())
         } else {
             RcDoc::nil()
         })
-        .append(RcDoc::text("in"))
+        .append(keyword("in", options))
         .append(RcDoc::softline())
 }
 
@@ -0,0 +1,218 @@
+use pretty::RcDoc;
+use sqlparser::ast::{Expr, Ident};
+
+use crate::doc::common::{ident_doc, interweave_comma, keyword, FormaDoc};
+use crate::doc::expr::expr_doc;
+use crate::doc::statement::{on_conflict_doc, returning_doc};
+use crate::doc::{statement_doc, RenderOptions};
+use crate::procedural::{Block, BlockStyle, Declaration};
+
+/// Transforms the given `Block` into an `RcDoc`.
+pub fn block_doc<'a>(block: Block, options: &RenderOptions) -> FormaDoc<'a> {
+    match block {
+        // Plain SQL statement, e.g. `select 1`.
+        Block::Statement(statement) => statement_doc(statement, options),
+        // Block, e.g. `begin select 1; end`.
+        Block::Begin { declarations, body } => declare_section_doc(declarations, options)
+            .append(keyword("begin", options))
+            .append(body_doc(body, options))
+            .append(RcDoc::hardline())
+            .append(keyword("end", options)),
+        // Conditional, e.g. `if x > 1 then select 1; end if`.
+        Block::If {
+            style,
+            branches,
+            otherwise,
+        } => if_doc(style, branches, otherwise, options),
+        // Loop, e.g. `while x > 1 loop select 1; end loop`.
+        Block::While {
+            style: BlockStyle::PlPgSql,
+            condition,
+            body,
+        } => condition_doc("while", condition, options)
+            .append(RcDoc::space())
+            .append(keyword("loop", options))
+            .append(body_doc(body, options))
+            .append(RcDoc::hardline())
+            .append(keyword("end loop", options)),
+        Block::While {
+            style: BlockStyle::TSql,
+            condition,
+            body,
+        } => condition_doc("while", condition, options).append(statement_body_doc(body, options)),
+        // T-SQL declaration, e.g. `declare @x int = 1`.
+        Block::Declare(declarations) => keyword("declare", options).append(RcDoc::space()).append(
+            interweave_comma(
+                declarations
+                    .into_iter()
+                    .map(|declaration| declaration_doc(declaration, "=", options)),
+                options,
+            )
+            .nest(options.nest_factor())
+            .group(),
+        ),
+        // Assignment, e.g. `x := 1`.
+        Block::Assign { variable, value } => assignment_doc(variable, ":=", value, options),
+        // Assignment, e.g. `set @x = @x + 1`.
+        Block::Set { variable, value } => keyword("set", options)
+            .append(RcDoc::space())
+            .append(assignment_doc(variable, "=", value, options)),
+        // Insert with clauses the parser does not understand, e.g.
+        // `insert into t1 values (1) on duplicate key update a = 1` or `... returning a`.
+        Block::Insert {
+            insert,
+            on_conflict,
+            returning,
+        } => statement_doc(insert, options)
+            .append(on_conflict_doc(
+                on_conflict.map(|on_conflict| *on_conflict),
+                options,
+            ))
+            .append(returning_doc(returning, options))
+            .group(),
+        // Return, e.g. `return x + 1`.
+        Block::Return(value) => keyword("return", options).append(match value {
+            Some(value) => RcDoc::space().append(expr_doc(value, options)),
+            None => RcDoc::nil(),
+        }),
+    }
+}
+
+/// Lays out a list of statements on lines of their own, indented one level and each terminated.
+fn body_doc<'a>(body: Vec<Block>, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::concat(body.into_iter().map(|block| {
+        RcDoc::hardline()
+            .append(block_doc(block, options))
+            .append(RcDoc::text(";"))
+    }))
+    .nest(options.nest_factor())
+}
+
+/// Lays out the single statement following a T-SQL `if`, `else` or `while`.
+///
+/// A `begin ... end` block starts on the next line at the same indentation, as is customary;
+/// any other statement is indented one level. The statement is left unterminated, so that the
+/// terminator of the enclosing statement follows it.
+fn statement_body_doc<'a>(mut body: Vec<Block>, options: &RenderOptions) -> FormaDoc<'a> {
+    match body.as_slice() {
+        [Block::Begin { .. }] => RcDoc::hardline().append(block_doc(body.remove(0), options)),
+        _ => RcDoc::concat(
+            body.into_iter()
+                .map(|block| RcDoc::hardline().append(block_doc(block, options))),
+        )
+        .nest(options.nest_factor()),
+    }
+}
+
+fn condition_doc<'a>(word: &str, condition: Expr, options: &RenderOptions) -> FormaDoc<'a> {
+    keyword(word, options)
+        .append(RcDoc::space())
+        .append(expr_doc(condition, options).nest(options.nest_factor()))
+        .group()
+}
+
+fn if_doc<'a>(
+    style: BlockStyle,
+    branches: Vec<(Expr, Vec<Block>)>,
+    otherwise: Option<Vec<Block>>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    match style {
+        BlockStyle::PlPgSql => RcDoc::intersperse(
+            branches
+                .into_iter()
+                .enumerate()
+                .map(|(position, (condition, body))| {
+                    condition_doc(
+                        if position == 0 { "if" } else { "elsif" },
+                        condition,
+                        options,
+                    )
+                    .append(RcDoc::space())
+                    .append(keyword("then", options))
+                    .append(body_doc(body, options))
+                }),
+            RcDoc::hardline(),
+        )
+        .append(match otherwise {
+            Some(otherwise) => RcDoc::hardline()
+                .append(keyword("else", options))
+                .append(body_doc(otherwise, options)),
+            None => RcDoc::nil(),
+        })
+        .append(RcDoc::hardline())
+        .append(keyword("end if", options)),
+        BlockStyle::TSql => RcDoc::concat(branches.into_iter().map(|(condition, body)| {
+            condition_doc("if", condition, options).append(statement_body_doc(body, options))
+        }))
+        .append(match otherwise {
+            Some(mut otherwise) => {
+                let else_doc = RcDoc::hardline().append(keyword("else", options));
+                match otherwise.as_slice() {
+                    // Chained conditions read as `else if`.
+                    [Block::If {
+                        style: BlockStyle::TSql,
+                        ..
+                    }] => else_doc
+                        .append(RcDoc::space())
+                        .append(block_doc(otherwise.remove(0), options)),
+                    _ => else_doc.append(statement_body_doc(otherwise, options)),
+                }
+            }
+            None => RcDoc::nil(),
+        }),
+    }
+}
+
+/// Lays out a PL/pgSQL `declare` section, one variable per line.
+fn declare_section_doc<'a>(
+    declarations: Vec<Declaration>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    if declarations.is_empty() {
+        RcDoc::nil()
+    } else {
+        keyword("declare", options)
+            .append(
+                RcDoc::concat(declarations.into_iter().map(|declaration| {
+                    RcDoc::hardline()
+                        .append(declaration_doc(declaration, ":=", options))
+                        .append(RcDoc::text(";"))
+                }))
+                .nest(options.nest_factor()),
+            )
+            .append(RcDoc::hardline())
+    }
+}
+
+fn declaration_doc<'a>(
+    Declaration {
+        name,
+        data_type,
+        default,
+    }: Declaration,
+    operator: &str,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    ident_doc(name, options)
+        .append(RcDoc::space())
+        .append(keyword(&data_type.to_string(), options))
+        .append(match default {
+            Some(default) => {
+                RcDoc::text(format!(" {} ", operator)).append(expr_doc(default, options))
+            }
+            None => RcDoc::nil(),
+        })
+}
+
+fn assignment_doc<'a>(
+    variable: Ident,
+    operator: &str,
+    value: Expr,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    ident_doc(variable, options)
+        .append(RcDoc::text(format!(" {} ", operator)))
+        .append(expr_doc(value, options).nest(options.nest_factor()))
+        .group()
+}
@@ -1,10 +1,14 @@
 use pretty::RcDoc;
 use sqlparser::ast::{Cte, Expr, Fetch, Offset, OffsetRows, Query};
 
-use crate::constants::NEST_FACTOR;
 use crate::doc::body::body_doc;
-use crate::doc::common::{interweave_comma, order_by_doc, parenthenized, FormaDoc};
+use crate::doc::common::{
+    clause_line, clause_separator, interweave_comma, keyword, order_by_doc, parenthenized, text,
+    FormaDoc,
+};
 use crate::doc::expr::expr_doc;
+use crate::doc::RenderOptions;
+use crate::scan::LimitStyle;
 
 /// Transforms the given `Query` into an `RcDoc`.
 pub fn query_doc<'a>(
@@ -16,74 +20,101 @@ pub fn query_doc<'a>(
         offset,
         fetch,
     }: Query,
+    options: &RenderOptions,
 ) -> FormaDoc<'a> {
     // CTEs.
-    ctes_doc(ctes)
+    ctes_doc(ctes, options)
         // Query body, e.g. `select * from t1 where x > 1`.
-        .append(body_doc(body))
+        .append(body_doc(body, options))
         // Order by.
         .append(if !order_by.is_empty() {
-            RcDoc::line()
-                .append(RcDoc::text("order by").append(RcDoc::line().nest(NEST_FACTOR)))
+            clause_line("order by", options)
+                .append(keyword("order by", options).append(clause_separator(options)))
                 .append(
-                    interweave_comma(order_by.into_iter().map(order_by_doc))
-                        .nest(NEST_FACTOR)
-                        .group(),
+                    interweave_comma(
+                        order_by
+                            .into_iter()
+                            .map(|order_by| order_by_doc(order_by, options)),
+                        options,
+                    )
+                    .nest(options.clause_nest())
+                    .group(),
                 )
         } else {
             RcDoc::nil()
         })
-        // Limit.
-        .append(limit_doc(limit))
-        // Offset.
-        .append(offset_doc(offset))
+        // Limit and offset.
+        .append(limit_doc(limit, offset, options))
         // Fetch.
-        .append(fetch_doc(fetch))
+        .append(fetch_doc(fetch, options))
         .group()
 }
 
-fn ctes_doc<'a>(ctes: Vec<Cte>) -> FormaDoc<'a> {
+fn ctes_doc<'a>(ctes: Vec<Cte>, options: &RenderOptions) -> FormaDoc<'a> {
     if !ctes.is_empty() {
-        RcDoc::text("with")
+        keyword("with", options)
             .append(RcDoc::space())
-            .append(interweave_comma(ctes.into_iter().map(
-                |Cte { alias, query }| {
+            .append(interweave_comma(
+                ctes.into_iter().map(|Cte { alias, query }| {
                     // Special-case CTEs alias handling.
-                    RcDoc::text(format!("{} as", alias.to_string()))
+                    text(alias.to_string(), options)
+                        .append(RcDoc::space())
+                        .append(keyword("as", options))
                         .append(RcDoc::softline())
-                        .append(parenthenized(query_doc(query)))
-                },
-            )))
-            .nest(NEST_FACTOR)
+                        .append(parenthenized(query_doc(query, options), options))
+                }),
+                options,
+            ))
+            .nest(options.nest_factor())
             .append(RcDoc::line().append(RcDoc::line()))
     } else {
         RcDoc::nil()
     }
 }
 
-fn limit_doc<'a>(limit: Option<Expr>) -> FormaDoc<'a> {
+fn limit_doc<'a>(
+    limit: Option<Expr>,
+    offset: Option<Offset>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
     if let Some(limit) = limit {
-        RcDoc::line()
-            .append(RcDoc::text("limit").append(RcDoc::line().nest(NEST_FACTOR)))
-            .append(RcDoc::text(limit.to_string()))
+        let limit_clause = clause_line("limit", options)
+            .append(keyword("limit", options).append(clause_separator(options)));
+        let offset = offset.map(|Offset { value, rows }| {
+            let (value, style) = options.unmark_offset(value);
+            (Offset { value, rows }, style)
+        });
+        match offset {
+            // Keep MySQL's `limit offset, count` as it was written.
+            Some((Offset { value, .. }, LimitStyle::Comma)) => {
+                limit_clause.append(text(format!("{}, {}", value, limit), options))
+            }
+            offset => limit_clause
+                .append(text(limit.to_string(), options))
+                .append(offset_doc(offset.map(|(offset, _)| offset), options)),
+        }
     } else {
-        RcDoc::nil()
+        offset_doc(offset, options)
     }
 }
 
-fn offset_doc<'a>(offset: Option<Offset>) -> FormaDoc<'a> {
+fn offset_doc<'a>(offset: Option<Offset>, options: &RenderOptions) -> FormaDoc<'a> {
     if let Some(Offset { value, rows }) = offset {
-        RcDoc::line().append(RcDoc::text(format!("offset {}", value)).append(match rows {
-            OffsetRows::None => RcDoc::nil(),
-            OffsetRows::Row => RcDoc::text(" row"),
-            OffsetRows::Rows => RcDoc::text(" rows"),
-        }))
+        clause_line("offset", options).append(
+            keyword("offset", options)
+                .append(text(format!(" {}", value), options))
+                .append(match rows {
+                    OffsetRows::None => RcDoc::nil(),
+                    OffsetRows::Row => keyword(" row", options),
+                    OffsetRows::Rows => keyword(" rows", options),
+                }),
+        )
     } else {
         RcDoc::nil()
     }
 }
 
-fn fetch_doc<'a>(fetch: Option<Fetch>) -> FormaDoc<'a> {
+fn fetch_doc<'a>(fetch: Option<Fetch>, options: &RenderOptions) -> FormaDoc<'a> {
     if let Some(Fetch {
         with_ties,
         percent,
@@ -91,26 +122,26 @@ fn fetch_doc<'a>(fetch: Option<Fetch>) -> FormaDoc<'a> {
     }) = fetch
     {
         let extension = if with_ties {
-            RcDoc::text("with ties")
+            keyword("with ties", options)
         } else {
-            RcDoc::text("only")
+            keyword("only", options)
         };
-        RcDoc::line().append(if let Some(quantity) = quantity {
+        clause_line("fetch", options).append(if let Some(quantity) = quantity {
             let percent = if percent {
-                RcDoc::space().append(RcDoc::text("percent"))
+                RcDoc::space().append(keyword("percent", options))
             } else {
                 RcDoc::nil()
             };
-            RcDoc::text("fetch first")
+            keyword("fetch first", options)
                 .append(RcDoc::space())
-                .append(expr_doc(quantity))
+                .append(expr_doc(quantity, options))
                 .append(percent)
                 .append(RcDoc::space())
-                .append(RcDoc::text("rows"))
+                .append(keyword("rows", options))
                 .append(RcDoc::space())
                 .append(extension)
         } else {
-            RcDoc::text("fetch first rows")
+            keyword("fetch first rows", options)
                 .append(RcDoc::space())
                 .append(extension)
         })
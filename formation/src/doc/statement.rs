@@ -0,0 +1,146 @@
+use pretty::RcDoc;
+use sqlparser::ast::{Assignment, Ident, ObjectName, Query, SelectItem, SetVariableValue};
+
+use crate::doc::body::select_item_doc;
+use crate::doc::common::{ident_doc, interweave_comma, keyword, parenthenized, text, FormaDoc};
+use crate::doc::expr::{expr_doc, value_doc};
+use crate::doc::query::query_doc;
+use crate::doc::RenderOptions;
+use crate::procedural::{ConflictTarget, OnConflict};
+
+/// Transforms an `insert` statement, e.g. `insert into t1 (a) values (1)`, into an `RcDoc`.
+pub fn insert_doc<'a>(
+    table_name: ObjectName,
+    columns: Vec<Ident>,
+    source: Query,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    keyword("insert into", options)
+        .append(RcDoc::space())
+        .append(text(table_name.to_string(), options))
+        .append(if !columns.is_empty() {
+            RcDoc::space().append(parenthenized(
+                interweave_comma(
+                    columns.into_iter().map(|column| ident_doc(column, options)),
+                    options,
+                ),
+                options,
+            ))
+        } else {
+            RcDoc::nil()
+        })
+        .append(RcDoc::line())
+        .append(query_doc(source, options))
+        .group()
+}
+
+/// Transforms the clause saying what an `insert` does with a conflicting row, e.g.
+/// `on duplicate key update a = 1` or `on conflict (a) do nothing`, into an `RcDoc`.
+pub fn on_conflict_doc<'a>(
+    on_conflict: Option<OnConflict>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    match on_conflict {
+        Some(OnConflict::DuplicateKeyUpdate(assignments)) => RcDoc::line()
+            .append(keyword("on duplicate key update", options))
+            .append(assignments_doc(assignments, options)),
+        Some(OnConflict::DoNothing(target)) => RcDoc::line()
+            .append(keyword("on conflict", options))
+            .append(conflict_target_doc(target, options))
+            .append(RcDoc::space())
+            .append(keyword("do nothing", options)),
+        Some(OnConflict::DoUpdate {
+            target,
+            assignments,
+            selection,
+        }) => RcDoc::line()
+            .append(keyword("on conflict", options))
+            .append(conflict_target_doc(target, options))
+            .append(RcDoc::space())
+            .append(keyword("do update set", options))
+            .append(assignments_doc(assignments, options))
+            .append(match selection {
+                Some(selection) => RcDoc::line()
+                    .append(keyword("where", options))
+                    .append(RcDoc::space())
+                    .append(expr_doc(selection, options)),
+                None => RcDoc::nil(),
+            }),
+        None => RcDoc::nil(),
+    }
+}
+
+/// Transforms the target of PostgreSQL's `on conflict`, e.g. `(a, b)`, into an `RcDoc`.
+fn conflict_target_doc<'a>(
+    target: Option<ConflictTarget>,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    match target {
+        Some(ConflictTarget::Columns(columns)) => RcDoc::space().append(parenthenized(
+            interweave_comma(
+                columns.into_iter().map(|column| ident_doc(column, options)),
+                options,
+            ),
+            options,
+        )),
+        Some(ConflictTarget::Constraint(name)) => RcDoc::space()
+            .append(keyword("on constraint", options))
+            .append(RcDoc::space())
+            .append(ident_doc(name, options)),
+        None => RcDoc::nil(),
+    }
+}
+
+/// Transforms the assignments of an upsert, e.g. `a = 1, b = 2`, into an `RcDoc`.
+fn assignments_doc<'a>(assignments: Vec<Assignment>, options: &RenderOptions) -> FormaDoc<'a> {
+    RcDoc::line()
+        .append(interweave_comma(
+            assignments.into_iter().map(|Assignment { id, value }| {
+                ident_doc(id, options)
+                    .append(RcDoc::text(" = "))
+                    .append(expr_doc(value, options))
+            }),
+            options,
+        ))
+        .nest(options.nest_factor())
+        .group()
+}
+
+/// Transforms PostgreSQL's `returning` clause, e.g. `returning a, b`, into an `RcDoc`.
+pub fn returning_doc<'a>(returning: Vec<SelectItem>, options: &RenderOptions) -> FormaDoc<'a> {
+    if returning.is_empty() {
+        return RcDoc::nil();
+    }
+    RcDoc::line().append(keyword("returning", options)).append(
+        RcDoc::line()
+            .append(interweave_comma(
+                returning
+                    .into_iter()
+                    .map(|select_item| select_item_doc(select_item, options)),
+                options,
+            ))
+            .nest(options.nest_factor())
+            .group(),
+    )
+}
+
+/// Transforms a `set` statement, e.g. `set @x = 1`, into an `RcDoc`.
+pub fn set_variable_doc<'a>(
+    local: bool,
+    variable: Ident,
+    value: SetVariableValue,
+    options: &RenderOptions,
+) -> FormaDoc<'a> {
+    if local {
+        keyword("set local", options)
+    } else {
+        keyword("set", options)
+    }
+    .append(RcDoc::space())
+    .append(ident_doc(variable, options))
+    .append(RcDoc::text(" = "))
+    .append(match value {
+        SetVariableValue::Ident(ident) => ident_doc(ident, options),
+        SetVariableValue::Literal(value) => value_doc(value, options),
+    })
+}
@@ -17,6 +17,11 @@ pub enum FormaError {
     #[error("Invalid SQL provided as input")]
     InvalidInput(#[from] ParserError),
 
+    /// A statement parsed as SQL which the formatter cannot lay out yet, given as the parser
+    /// writes it.
+    #[error("Unsupported statement: `{0}`")]
+    UnsupportedStatement(String),
+
     /// Formatting would occur, i.e. when `check` is `true`.
     #[error("Check failed; would format SQL")]
     WouldFormat,
@@ -28,4 +33,17 @@ pub enum FormaError {
     /// A UTF-8 failure.
     #[error("A UTF8 error occurred")]
     Utf8Failure(#[from] FromUtf8Error),
+
+    /// Formatting options were given which cannot be used together.
+    #[error("Option `{0}` cannot be combined with `{1}`")]
+    ConflictingOptions(&'static str, &'static str),
+
+    /// An unrecognized value was given for a formatting option.
+    #[error("Invalid value `{value}` for option `{option}`")]
+    InvalidOption {
+        /// The name of the option.
+        option: &'static str,
+        /// The value that was given.
+        value: String,
+    },
 }
@@ -2,29 +2,37 @@
 //!
 //! This module provides a formatting function [`format`] which is intended to be used to format SQL
 //! strings in an opinionated fashion. The function is only configurable in a minimal way by
-//! design.
+//! design. Where a team's conventions demand more, [`format_with_options`] accepts a full set of
+//! [`FormatOptions`].
 //!
 //! [`format`]: ../format/fn.format.html
+//! [`format_with_options`]: ../format/fn.format_with_options.html
+//! [`FormatOptions`]: ../options/struct.FormatOptions.html
 
-use sqlparser::ast::Statement;
-use sqlparser::parser::Parser;
+use std::iter;
+use std::path::Path;
 
-use crate::dialect::TemplatedDialect;
-use crate::doc::render_statement;
+use log::warn;
+use sqlparser::parser::ParserError;
+use sqlparser::tokenizer::Tokenizer;
+
+use crate::detect::detect_dialect;
+use crate::dialect::parser_dialect;
+use crate::doc::{render_block, RenderOptions};
 use crate::error::{self, FormaError};
+use crate::mask::Masks;
+use crate::options::{Dialect, FormatOptions, IndentStyle, StyleEdition};
+use crate::procedural::{self, Block};
+use crate::scan::{self, Scan};
+use crate::split::{self, Batch, Part, Routine};
+use crate::template::{self, Segment};
 
 fn format_statement(
-    sql: &str,
-    statement: Statement,
-    check: bool,
-    max_width: usize,
+    block: Block,
+    options: &RenderOptions,
+    delimiter: &str,
 ) -> error::Result<String> {
-    let pretty = format!("{};\n", render_statement(statement, max_width)?);
-    if check && pretty != sql {
-        Err(FormaError::WouldFormat)
-    } else {
-        Ok(pretty)
-    }
+    Ok(format!("{}{}\n", render_block(block, options)?, delimiter))
 }
 
 /// Formats a given SQL string in accordance with the given maximum width.
@@ -36,12 +44,17 @@ fn format_statement(
 ///
 /// # Errors
 ///
-/// Returns a [`FormaError::InvalidInput`] if the parser cannot parse the provided input.
+/// Returns a [`FormaError::InvalidInput`] if the parser cannot parse the provided input, or a
+/// [`FormaError::UnsupportedStatement`] if it parses a statement that cannot be laid out yet, e.g.
+/// `update`. Options which cannot be used together give a [`FormaError::ConflictingOptions`].
 ///
 /// If `check` is `true`, will return a [`FormaError::WouldFormat`] if the provided input would be
 /// formatted.
 ///
 /// [`FormaError::InvalidInput`]: ../error/enum.FormaError.html#variant.InvalidInput
+/// [`FormaError::UnsupportedStatement`]:
+/// ../error/enum.FormaError.html#variant.UnsupportedStatement
+/// [`FormaError::ConflictingOptions`]: ../error/enum.FormaError.html#variant.ConflictingOptions
 /// [`FormaError::WouldFormat`]: ../error/enum.FormaError.html#variant.WouldFormat
 ///
 /// # Example
@@ -55,12 +68,251 @@ fn format_statement(
 /// );
 /// ```
 pub fn format(sql: &str, check: bool, max_width: usize) -> error::Result<Vec<String>> {
-    let dialect = TemplatedDialect {};
-    let statements = Parser::parse_sql(&dialect, sql)?;
+    format_with_options(
+        sql,
+        check,
+        &FormatOptions {
+            max_width,
+            ..FormatOptions::default()
+        },
+    )
+}
+
+/// Formats a given SQL string in accordance with the given options.
+///
+/// This behaves exactly as [`format`], save that the layout is controlled by `options` rather
+/// than by the maximum width alone.
+///
+/// [`format`]: ../format/fn.format.html
+///
+/// # Example
+///
+/// ```
+/// use formation::format_with_options;
+/// use formation::options::{FormatOptions, IndentStyle};
+/// let sql = "SELECT id, name FROM users WHERE id = 1;";
+/// let options = FormatOptions {
+///     max_width: 10,
+///     indent_width: 4,
+///     indent_style: IndentStyle::Tabs,
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(
+///     format_with_options(sql, false, &options).unwrap(),
+///     vec!["select\n\tid,\n\tname\nfrom\n\tusers\nwhere\n\tid = 1;\n".to_owned()]
+/// );
+/// ```
+pub fn format_with_options(
+    sql: &str,
+    check: bool,
+    options: &FormatOptions,
+) -> error::Result<Vec<String>> {
+    options.validate()?;
+    let batches = match options.dialect {
+        // `GO` and `DELIMITER` lines are not SQL, so each batch is parsed separately and the lines
+        // kept as they were.
+        Dialect::MsSql => split::go_batches(sql),
+        Dialect::MySql => split::delimiter_batches(sql),
+        _ => vec![Batch {
+            sql,
+            delimiter: ";",
+            separator: None,
+        }],
+    };
+    let mut pretty_statements = vec![];
+    for Batch {
+        sql,
+        delimiter,
+        separator,
+    } in batches
+    {
+        pretty_statements.extend(format_batch(sql, options, delimiter)?);
+        if let Some(separator) = separator {
+            pretty_statements.push(format!("{}\n", separator));
+        }
+    }
+    // The input is compared as a whole, as formatting may also change what lies between
+    // statements.
+    if check && pretty_statements.concat() != sql {
+        Err(FormaError::WouldFormat)
+    } else {
+        Ok(pretty_statements)
+    }
+}
+
+/// Formats a given SQL string whose dialect isn't known.
+///
+/// The dialect is guessed using [`detect_dialect`], taking hints from `path`, and the dialect of
+/// `options` is ignored. Should the SQL fail to parse as the guessed dialect, every other dialect
+/// is tried in turn. Returns the dialect which was used along with the formatted statements.
+///
+/// Detection is only done from style edition 0.4. Under earlier editions, the dialect of
+/// `options` is used as is, so that SQL keeps the layout it had before dialects were detected.
+///
+/// # Errors
+///
+/// Returns the error of the guessed dialect if no dialect can parse the provided input. Otherwise,
+/// errors as [`format_with_options`] does.
+///
+/// [`detect_dialect`]: ../detect/fn.detect_dialect.html
+/// [`format_with_options`]: ../format/fn.format_with_options.html
+pub fn format_detected(
+    sql: &str,
+    path: Option<&Path>,
+    check: bool,
+    options: &FormatOptions,
+) -> error::Result<(Dialect, Vec<String>)> {
+    if options.style_edition < StyleEdition::V0_4 {
+        return Ok((options.dialect, format_with_options(sql, check, options)?));
+    }
+    let guess = detect_dialect(path, sql);
+    let mut first_error = None;
+    for dialect in iter::once(guess).chain(Dialect::ALL.iter().copied().filter(|&d| d != guess)) {
+        let options = FormatOptions {
+            dialect,
+            ..options.clone()
+        };
+        match format_with_options(sql, check, &options) {
+            Ok(pretty_statements) => return Ok((dialect, pretty_statements)),
+            Err(error @ FormaError::InvalidInput(_)) => {
+                first_error.get_or_insert(error);
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Err(first_error.expect("at least one dialect is tried"))
+}
+
+fn format_batch(sql: &str, options: &FormatOptions, delimiter: &str) -> error::Result<Vec<String>> {
+    let mut pretty_statements = vec![];
+    if delimiter != ";" {
+        for sql in split::delimited_statements(sql, delimiter)
+            .into_iter()
+            .filter(|sql| !sql.trim().is_empty())
+        {
+            if split::is_stored_program(sql) {
+                warn!("Leaving a stored program as written, as it cannot be parsed");
+                pretty_statements.push(format!("{}{}\n", sql.trim(), delimiter));
+            } else {
+                pretty_statements.extend(format_statements(sql, options, delimiter)?);
+            }
+        }
+        return Ok(pretty_statements);
+    }
+    for segment in template::segments(sql, &options.templates) {
+        match segment {
+            Segment::Sql(sql) if options.dialect == Dialect::PostgreSql => {
+                for part in split::routine_parts(sql) {
+                    match part {
+                        Part::Sql(sql) => {
+                            pretty_statements.extend(format_statements(sql, options, delimiter)?)
+                        }
+                        Part::Routine(routine) => {
+                            pretty_statements.push(format_routine(routine, options))
+                        }
+                    }
+                }
+            }
+            Segment::Sql(sql) => {
+                pretty_statements.extend(format_statements(sql, options, delimiter)?)
+            }
+            Segment::Tag { indent, tag } => pretty_statements.push(format!("{}{}\n", indent, tag)),
+            Segment::Verbatim(sql) => {
+                warn!(
+                    "Leaving a statement as written, as template tags within it cannot be parsed"
+                );
+                pretty_statements.push(format!("{}\n", sql));
+            }
+        }
+    }
+    Ok(pretty_statements)
+}
+
+/// Formats a statement with a dollar-quoted body, e.g. `create function`.
+///
+/// The parser cannot make sense of the statement itself, which is kept as written. Its body,
+/// when written in SQL or PL/pgSQL, is formatted in turn and indented within the quotes. A body
+/// which does not parse is left as written.
+fn format_routine(routine: Routine, options: &FormatOptions) -> String {
+    let Routine {
+        header,
+        tag,
+        body,
+        footer,
+    } = routine;
+    let original = format!("{}{}{}{}{};\n", header.trim_start(), tag, body, tag, footer);
+    let body_options = FormatOptions {
+        max_width: options.max_width.saturating_sub(options.indent_width),
+        ..options.clone()
+    };
+    let pretty_body = match routine.language().as_deref() {
+        Some("sql") | Some("plpgsql") => match format_with_options(body, false, &body_options) {
+            Ok(pretty_statements) => Some(pretty_statements.concat()),
+            Err(error) => {
+                warn!(
+                    "Leaving a routine body as written, as it could not be formatted: {}",
+                    error
+                );
+                None
+            }
+        },
+        _ => None,
+    };
+    match pretty_body {
+        Some(pretty_body) => {
+            let indent = match options.indent_style {
+                IndentStyle::Spaces => " ".repeat(options.indent_width),
+                IndentStyle::Tabs => "\t".to_owned(),
+            };
+            let indented: String = pretty_body
+                .lines()
+                .map(|line| match line {
+                    "" => "\n".to_owned(),
+                    line => format!("{}{}\n", indent, line),
+                })
+                .collect();
+            format!(
+                "{} {}\n{}{}{};\n",
+                header.trim(),
+                tag,
+                indented,
+                tag,
+                footer.trim_end()
+            )
+        }
+        None => original,
+    }
+}
+
+fn format_statements(
+    sql: &str,
+    options: &FormatOptions,
+    delimiter: &str,
+) -> error::Result<Vec<String>> {
+    let dialect = parser_dialect(options.dialect);
+    let mut masks = Masks::new(sql);
+    let masked = masks.mask_templates(sql, &options.templates);
+    let masked = masks.mask_double_quoted_strings(&masked, options.dialect);
+    let tokens = Tokenizer::new(&*dialect, &masked)
+        .tokenize()
+        .map_err(ParserError::from)?;
+    let tokens = masks.mask_paths(&tokens, options.dialect);
+    let tokens = masks.mask_placeholders(&tokens, options.dialect);
+    let (scan, tokens) = Scan::new(&tokens, options.dialect);
+    scan::reject_distinct_on(&tokens)?;
+    let blocks = match options.dialect {
+        // PL/pgSQL declares variables in a section ahead of `begin`, T-SQL in statements of their
+        // own.
+        Dialect::PostgreSql => procedural::parse_blocks(tokens, true)?,
+        Dialect::MsSql => procedural::parse_blocks(tokens, false)?,
+        Dialect::MySql => procedural::parse_statements(tokens, true)?,
+        _ => procedural::parse_statements(tokens, false)?,
+    };
+    let options = RenderOptions::new(options, scan, masks);
     let mut pretty_statements: Vec<String> = vec![];
 
-    for statement in statements {
-        let pretty_statement = format_statement(sql, statement, check, max_width)?;
+    for block in blocks {
+        let pretty_statement = format_statement(block, &options, delimiter)?;
         pretty_statements.push(pretty_statement);
     }
 
@@ -70,14 +322,16 @@ pub fn format(sql: &str, check: bool, max_width: usize) -> error::Result<Vec<Str
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::{
+        AliasStyle, CommaPosition, Layout, StyleEdition, TemplateDelimiters, TemplateKind,
+    };
     use pretty_assertions::assert_eq;
-    use sqlparser::ast::{Expr, Query, Select, SelectItem, SetExpr, Value};
+    use sqlparser::ast::{Expr, Query, Select, SelectItem, SetExpr, Statement, Value};
 
     const MAX_WIDTH: usize = 100;
 
     #[test]
     fn test_format_statement() {
-        let sql_string = "SELECT 42;";
         let statement = Statement::Query(Box::new(Query {
             body: SetExpr::Select(Box::new(Select {
                 distinct: false,
@@ -97,7 +351,19 @@ mod tests {
             order_by: vec![],
         }));
         assert_eq!(
-            format_statement(sql_string, statement, false, MAX_WIDTH).unwrap(),
+            format_statement(
+                Block::Statement(statement),
+                &RenderOptions::new(
+                    &FormatOptions {
+                        max_width: MAX_WIDTH,
+                        ..FormatOptions::default()
+                    },
+                    Scan::new(&[], Dialect::Templated).0,
+                    Masks::new("")
+                ),
+                ";"
+            )
+            .unwrap(),
             "select 42;\n".to_owned()
         );
     }
@@ -111,11 +377,818 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_leading_comma() {
+        let sql_string =
+            "SELECT col_a, col_b, col_c FROM t1 GROUP BY col_a, col_b ORDER BY col_a, col_b";
+        let options = FormatOptions {
+            max_width: 10,
+            comma_position: CommaPosition::Leading,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![concat!(
+                "select\n  col_a\n  , col_b\n  , col_c\n",
+                "from\n  t1\n",
+                "group by\n  col_a\n  , col_b\n",
+                "order by\n  col_a\n  , col_b;\n"
+            )
+            .to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_river() {
+        let sql_string = concat!(
+            "SELECT a.title, a.release_date, count(*) AS total FROM albums AS a ",
+            "JOIN artists AS b ON a.artist_id = b.id AND b.active = true ",
+            "WHERE a.title = 'Charcoal Lane' OR a.title = 'The New Danger' ",
+            "GROUP BY a.title, a.release_date HAVING count(*) > 1 ORDER BY a.title LIMIT 10"
+        );
+        let options = FormatOptions {
+            max_width: 50,
+            layout: Layout::River,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![concat!(
+                "select a.title, a.release_date, count(*) as total\n",
+                "  from albums as a\n",
+                "       join artists as b\n",
+                "        on a.artist_id = b.id\n",
+                "       and b.active = true\n",
+                " where a.title = 'Charcoal Lane'\n",
+                "    or a.title = 'The New Danger'\n",
+                " group by a.title, a.release_date\n",
+                "having count(*) > 1\n",
+                " order by a.title\n",
+                " limit 10;\n"
+            )
+            .to_owned()]
+        );
+        // The river is aligned to columns which tabs cannot be relied upon to reach.
+        assert!(matches!(
+            format_with_options(
+                sql_string,
+                false,
+                &FormatOptions {
+                    indent_style: IndentStyle::Tabs,
+                    ..options
+                }
+            ),
+            Err(FormaError::ConflictingOptions(..))
+        ));
+    }
+
+    #[test]
+    fn test_format_implicit_aliases() {
+        let sql_string = "SELECT count(*) AS total, a AS \"from\", b AS select FROM t1 AS t";
+        let options = FormatOptions {
+            column_alias: AliasStyle::Implicit,
+            table_alias: AliasStyle::Implicit,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec!["select count(*) total, a \"from\", b as select from t1 t;\n".to_owned()]
+        );
+        // Words a dialect reserves beyond the shared keywords keep their `as` in that dialect.
+        let sql_string = "SELECT a AS div, b AS qualify FROM t1";
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec!["select a div, b qualify from t1;\n".to_owned()]
+        );
+        assert_eq!(
+            format_with_options(
+                sql_string,
+                false,
+                &FormatOptions {
+                    dialect: Dialect::MySql,
+                    ..options.clone()
+                }
+            )
+            .unwrap(),
+            vec!["select a as div, b qualify from t1;\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_preserve_aliases() {
+        let sql_string = "SELECT a AS x, b y FROM t1 AS t JOIN t2 u ON t.id = u.id";
+        let options = FormatOptions {
+            column_alias: AliasStyle::Preserve,
+            table_alias: AliasStyle::Preserve,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec!["select a as x, b y from t1 as t join t2 u on t.id = u.id;\n".to_owned()]
+        );
+        // Aliases are told apart by where they appear, not by name.
+        assert_eq!(
+            format_with_options("SELECT a AS t, CAST(b AS x) x FROM t1 t", false, &options)
+                .unwrap(),
+            vec!["select a as t, cast(b as x) x from t1 t;\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_style_edition() {
+        // Edition 0.3 lays out SQL as `formation` 0.3 did, whatever has been added since.
+        let sql_string = "SELECT * FROM a, b AS bb, c cc WHERE a.x = bb.y LIMIT 10 OFFSET 5";
+        let options = FormatOptions {
+            max_width: 20,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![concat!(
+                "select\n  *\nfrom\n  a,\n  b as bb,\n  c as cc\n",
+                "where\n  a.x = bb.y\nlimit\n  10\noffset 5;\n"
+            )
+            .to_owned()]
+        );
+
+        let sql_string = "SELECT {{Date}}, {{ Label }} FROM t1 WHERE a = @Var AND b = '{{Date}}'";
+        assert_eq!(
+            format(sql_string, false, MAX_WIDTH).unwrap(),
+            vec![
+                "select {{date}}, {{ Label }} from t1 where a = @var and b = '{{Date}}';\n"
+                    .to_owned()
+            ]
+        );
+        let options = FormatOptions {
+            style_edition: StyleEdition::V0_4,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![
+                "select {{Date}}, {{ Label }} from t1 where a = @Var and b = '{{Date}}';\n"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_dialect() {
+        let sql_string = r#"SELECT "MyCol" FROM t1"#;
+        assert_eq!(
+            format_with_options(sql_string, false, &FormatOptions::default()).unwrap(),
+            vec![r#"select "mycol" from t1;"#.to_owned() + "\n"]
+        );
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![r#"select "MyCol" from t1;"#.to_owned() + "\n"]
+        );
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options("SELECT {{a}} FROM t1", false, &options).unwrap(),
+            vec!["select {{a}} from t1;\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_bigquery() {
+        let sql_string = "SELECT t.Name FROM `my-project.Sales.Orders` AS t";
+        let options = FormatOptions {
+            dialect: Dialect::BigQuery,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec!["select t.name from `my-project.Sales.Orders` as t;\n".to_owned()]
+        );
+        let format = |sql_string| format_with_options(sql_string, false, &options).unwrap();
+        assert_eq!(
+            format("SELECT STRUCT<a INT64, b ARRAY<STRING>>(1, c), ARRAY<INT64>[1, 2] FROM t1"),
+            vec![
+                "select struct<a int64, b array<string>>(1, c), array<int64>[1, 2] from t1;\n"
+                    .to_owned()
+            ]
+        );
+        assert_eq!(
+            format("SELECT CAST(a AS ARRAY<STRING>), SAFE_CAST(b AS INT64) FROM t1"),
+            vec!["select cast(a as array<string>), safe_cast(b as int64) from t1;\n".to_owned()]
+        );
+        assert_eq!(
+            format("SELECT x, o FROM UNNEST(a) AS x WITH OFFSET AS o, UNNEST(b) WITH OFFSET"),
+            vec![
+                "select x, o from unnest(a) as x with offset as o, unnest(b) with offset;\n"
+                    .to_owned()
+            ]
+        );
+        assert_eq!(
+            format("SELECT * EXCEPT (a, b), t.* REPLACE (a + 1 AS a) FROM t1 AS t"),
+            vec!["select * except (a, b), t.* replace (a + 1 as a) from t1 as t;\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_mssql_batches() {
+        let sql_string = "SELECT TOP 5 [Name] FROM [dbo].[Users]\nGO\nSET @limit = 10\ngo 2\n";
+        let options = FormatOptions {
+            dialect: Dialect::MsSql,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![
+                "select top (5) [Name] from [dbo].[Users];\n".to_owned(),
+                "GO\n".to_owned(),
+                "set @limit = 10;\n".to_owned(),
+                "go 2\n".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_set() {
+        let options = FormatOptions {
+            dialect: Dialect::Generic,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options("SET x = y + 1; SELECT x", false, &options).unwrap(),
+            vec!["set x = y + 1;\n".to_owned(), "select x;\n".to_owned()]
+        );
+        assert!(format_with_options("SET x = y + 1 SELECT x", false, &options).is_err());
+    }
+
+    #[test]
+    fn test_format_mysql() {
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(
+                "SELECT `Id` FROM `Users` LIMIT 10, 20;\nSELECT 1 LIMIT 5 OFFSET 2",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec![
+                "select `Id` from `Users` limit 10, 20;\n".to_owned(),
+                "select 1 limit 5 offset 2;\n".to_owned(),
+            ]
+        );
+        // Each `limit` keeps its own form, however the queries are nested.
+        assert_eq!(
+            format_with_options(
+                "SELECT * FROM (SELECT a FROM t1 LIMIT 5) AS s LIMIT 10, 20",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec!["select * from (select a from t1 limit 5) as s limit 10, 20;\n".to_owned()]
+        );
+        assert_eq!(
+            format_with_options(
+                "SELECT * FROM (SELECT a FROM t1 LIMIT 1, 5) AS s LIMIT 10 OFFSET 20",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec![
+                "select * from (select a from t1 limit 1, 5) as s limit 10 offset 20;\n".to_owned()
+            ]
+        );
+        // `"` quotes strings, which keep their quotes and case.
+        assert_eq!(
+            format_with_options(
+                "SELECT \"It's\", 'a' AS \"B\" FROM t1 WHERE c LIKE \"%X%\"",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec!["select \"It's\", 'a' as \"B\" from t1 where c like \"%X%\";\n".to_owned()]
+        );
+        assert_eq!(
+            format_with_options(
+                "DELIMITER $$\nSELECT '$$'$$\nSELECT 2$$\nDELIMITER ;\nSELECT 3;\n",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec![
+                "DELIMITER $$\n".to_owned(),
+                "select '$$'$$\n".to_owned(),
+                "select 2$$\n".to_owned(),
+                "DELIMITER ;\n".to_owned(),
+                "select 3;\n".to_owned(),
+            ]
+        );
+        // Stored programs are kept as written, as their bodies cannot be parsed.
+        assert_eq!(
+            format_with_options(
+                "DELIMITER $$\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND$$\nSELECT 2$$\n",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec![
+                "DELIMITER $$\n".to_owned(),
+                "CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND$$\n".to_owned(),
+                "select 2$$\n".to_owned(),
+            ]
+        );
+        assert_eq!(
+            format_with_options(
+                concat!(
+                    "INSERT INTO orders (id, total) VALUES (1, 2) ",
+                    "ON DUPLICATE KEY UPDATE total = total + VALUES(total), updated = NOW()"
+                ),
+                false,
+                &FormatOptions {
+                    max_width: 40,
+                    ..options
+                }
+            )
+            .unwrap(),
+            vec![concat!(
+                "insert into orders (id, total)\n",
+                "values (1, 2)\n",
+                "on duplicate key update\n",
+                "  total = total + values(total),\n",
+                "  updated = now();\n"
+            )
+            .to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_detected() {
+        // Edition 0.3 keeps to the configured dialect.
+        assert_eq!(
+            format_detected(
+                "SELECT \"Id\"::INT FROM t1",
+                None,
+                false,
+                &FormatOptions::default()
+            )
+            .unwrap(),
+            (
+                Dialect::Templated,
+                vec!["select cast(\"id\" as int) from t1;\n".to_owned()]
+            )
+        );
+        let options = FormatOptions {
+            style_edition: StyleEdition::V0_4,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_detected("SELECT \"Id\"::INT FROM t1", None, false, &options).unwrap(),
+            (
+                Dialect::PostgreSql,
+                vec!["select \"Id\"::int from t1;\n".to_owned()]
+            )
+        );
+        // Guessed as T-SQL by its path, but only MySQL accepts `limit offset, count`.
+        assert_eq!(
+            format_detected(
+                "SELECT a FROM t1 LIMIT 10, 20",
+                Some(Path::new("query.tsql")),
+                false,
+                &options
+            )
+            .unwrap(),
+            (
+                Dialect::MySql,
+                vec!["select a from t1 limit 10, 20;\n".to_owned()]
+            )
+        );
+        assert!(format_detected("SELECT (", None, false, &options).is_err());
+    }
+
+    #[test]
+    fn test_format_placeholders() {
+        let sql_string = concat!(
+            "SELECT * FROM Users WHERE Id = $1 AND Name = ? AND Email = :Email ",
+            "AND Org = %(org_id)s AND Team = %s AND Role = @Role"
+        );
+        assert_eq!(
+            format(sql_string, false, MAX_WIDTH).unwrap(),
+            vec![concat!(
+                "select\n  *\nfrom\n  Users\nwhere\n  id = $1 and name = ? and email = :Email ",
+                "and org = %(org_id)s and team = %s and role = @role;\n"
+            )
+            .to_owned()]
+        );
+        // Lines are fitted with the placeholders as written.
+        let sql_string =
+            "select * from users where id = $1 and name = ? and email = :email and org = %s;";
+        assert_eq!(
+            format(sql_string, false, 83).unwrap(),
+            vec![format!("{}\n", sql_string)]
+        );
+    }
+
+    #[test]
+    fn test_format_templates() {
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            templates: vec![
+                TemplateDelimiters::new("<%=", "%>", TemplateKind::Expression),
+                TemplateDelimiters::new("<%", "%>", TemplateKind::Statement),
+            ],
+            ..FormatOptions::default()
+        };
+        let sql_string = concat!(
+            "<% if incremental %>\n",
+            "SELECT ID FROM <%= source %> WHERE Updated > '<%= since %>';\n",
+            "<% end %>"
+        );
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![
+                "<% if incremental %>\n".to_owned(),
+                "select id from <%= source %> where updated > '<%= since %>';\n".to_owned(),
+                "<% end %>\n".to_owned(),
+            ]
+        );
+
+        // Statements with tags inside them aren't formatted yet, and are kept as written.
+        let sql_string = concat!(
+            "SELECT a {% if b %}, B {% endif %}\n",
+            "FROM   t1;\n",
+            "{% if c %}SELECT c FROM t2;{% endif %}"
+        );
+        assert_eq!(
+            format(sql_string, false, MAX_WIDTH).unwrap(),
+            vec![
+                "SELECT a {% if b %}, B {% endif %}\nFROM   t1;\n".to_owned(),
+                "{% if c %}\n".to_owned(),
+                "select c from t2;\n".to_owned(),
+                "{% endif %}\n".to_owned(),
+            ]
+        );
+
+        // Mustache sections are statements, though written like expressions.
+        let sql_string = "{{#tables}}\nSELECT * FROM {{name}};\n{{/tables}}";
+        assert_eq!(
+            format(sql_string, false, MAX_WIDTH).unwrap(),
+            vec![
+                "{{#tables}}\n".to_owned(),
+                "select * from {{name}};\n".to_owned(),
+                "{{/tables}}\n".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_custom_templates() {
+        let options = FormatOptions {
+            templates: vec![
+                TemplateDelimiters::new("${", "}", TemplateKind::Expression),
+                TemplateDelimiters::new("[[", "]]", TemplateKind::Expression),
+            ],
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(
+                "SELECT A, ${column} FROM [[schema]].Orders WHERE Id = ${id}",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec!["select a, ${column} from [[schema]].Orders where id = ${id};\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_routines() {
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        let sql_string = concat!(
+            "SELECT 1;\n",
+            "CREATE FUNCTION one() RETURNS int AS $$ SELECT 1 $$ LANGUAGE sql;\n",
+            "CREATE FUNCTION two() RETURNS int AS $body$ SELECT ( $body$ LANGUAGE sql;\n",
+            "CREATE FUNCTION f() RETURNS void AS $$ UPDATE t SET a = 1 $$ LANGUAGE plpgsql;\n"
+        );
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![
+                "select 1;\n".to_owned(),
+                "CREATE FUNCTION one() RETURNS int AS $$\n  select 1;\n$$ LANGUAGE sql;\n"
+                    .to_owned(),
+                "CREATE FUNCTION two() RETURNS int AS $body$ SELECT ( $body$ LANGUAGE sql;\n"
+                    .to_owned(),
+                "CREATE FUNCTION f() RETURNS void AS $$ UPDATE t SET a = 1 $$ LANGUAGE plpgsql;\n"
+                    .to_owned(),
+            ]
+        );
+        // Only routines have their dollar-quoted strings taken as bodies.
+        assert!(matches!(
+            format_with_options("SELECT $$a$$ FROM t1", false, &options),
+            Err(FormaError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_procedural() {
+        let options = FormatOptions {
+            dialect: Dialect::MsSql,
+            ..FormatOptions::default()
+        };
+        let sql_string = concat!(
+            "DECLARE @i INT = 0\n",
+            "WHILE @i < 10 BEGIN SET @i = @i + 1; ",
+            "IF @i > 5 SELECT id FROM users WHERE id = @i ELSE IF @i = 2 RETURN END"
+        );
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![
+                "declare @i int = 0;\n".to_owned(),
+                concat!(
+                    "while @i < 10\n",
+                    "begin\n",
+                    "  set @i = @i + 1;\n",
+                    "  if @i > 5\n",
+                    "    select id from users where id = @i\n",
+                    "  else if @i = 2\n",
+                    "    return;\n",
+                    "end;\n"
+                )
+                .to_owned(),
+            ]
+        );
+        // Statements end ahead of `else` and `end`, rather than taking either for an alias.
+        assert_eq!(
+            format_with_options("IF 1 = 1 SELECT 1 ELSE SELECT 2", false, &options).unwrap(),
+            vec!["if 1 = 1\n  select 1\nelse\n  select 2;\n".to_owned()]
+        );
+        assert_eq!(
+            format_with_options(
+                "IF 1 = 1 BEGIN SELECT 1 END ELSE BEGIN SELECT 2 SELECT 3 END",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec![concat!(
+                "if 1 = 1\n",
+                "begin\n",
+                "  select 1;\n",
+                "end\n",
+                "else\n",
+                "begin\n",
+                "  select 2;\n",
+                "  select 3;\n",
+                "end;\n"
+            )
+            .to_owned()]
+        );
+        assert_eq!(
+            format_with_options(
+                "WHILE 1 = 1 BEGIN SELECT CASE WHEN a THEN 1 ELSE 2 END FROM t1 END",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec![concat!(
+                "while 1 = 1\n",
+                "begin\n",
+                "  select case when a then 1 else 2 end from t1;\n",
+                "end;\n"
+            )
+            .to_owned()]
+        );
+
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        let sql_string = concat!(
+            "DECLARE y INT := 1; BEGIN IF x > 1 THEN y := x * 2; ELSIF x = 0 THEN RETURN 0; ",
+            "ELSE WHILE y < 10 LOOP y := y + 1; END LOOP; END IF; RETURN y; END;"
+        );
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec![concat!(
+                "declare\n",
+                "  y int := 1;\n",
+                "begin\n",
+                "  if x > 1 then\n",
+                "    y := x * 2;\n",
+                "  elsif x = 0 then\n",
+                "    return 0;\n",
+                "  else\n",
+                "    while y < 10 loop\n",
+                "      y := y + 1;\n",
+                "    end loop;\n",
+                "  end if;\n",
+                "  return y;\n",
+                "end;\n"
+            )
+            .to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_unsupported_statements() {
+        let options = FormatOptions {
+            dialect: Dialect::MsSql,
+            ..FormatOptions::default()
+        };
+        assert!(matches!(
+            format_with_options("BEGIN UPDATE t SET a = 1; END", false, &options),
+            Err(FormaError::UnsupportedStatement(statement)) if statement == "UPDATE t SET a = 1"
+        ));
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        assert!(matches!(
+            format_with_options("begin; select 1; commit;", false, &options),
+            Err(FormaError::UnsupportedStatement(_))
+        ));
+        // Only dialects with a procedural extension parse control flow.
+        assert!(matches!(
+            format("if 1 select 1", false, MAX_WIDTH),
+            Err(FormaError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_double_colon_casts() {
+        let sql_string = "SELECT a::INT, CAST(b AS TEXT), (a + b)::BIGINT FROM t1";
+        assert_eq!(
+            format(sql_string, false, MAX_WIDTH).unwrap(),
+            vec![concat!(
+                "select cast(a as int), cast(b as text), cast((a + b) as bigint) ",
+                "from t1;\n"
+            )
+            .to_owned()]
+        );
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec!["select a::int, cast(b as text), (a + b)::bigint from t1;\n".to_owned()]
+        );
+        // Each cast keeps its own form, however the casts are nested.
+        assert_eq!(
+            format_with_options(
+                "SELECT CAST(a AS INT)::TEXT, CAST(b::INT AS TEXT), c::INT::TEXT",
+                false,
+                &options
+            )
+            .unwrap(),
+            vec!["select cast(a as int)::text, cast(b::int as text), c::int::text;\n".to_owned()]
+        );
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_options(sql_string, false, &options).unwrap(),
+            vec!["select a::int, cast(b as text), (a + b)::bigint from t1;\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_postgresql() {
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        let format = |sql_string| format_with_options(sql_string, false, &options).unwrap();
+        // JSON path operators are written without spaces, other operators with them.
+        assert_eq!(
+            format("SELECT a -> 'b' ->> 'c', a#>'{b,c}', (a->>'b')::INT FROM t1"),
+            vec!["select a->'b'->>'c', a#>'{b,c}', (a->>'b')::int from t1;\n".to_owned()]
+        );
+        assert_eq!(
+            format("SELECT * FROM t1 WHERE a@>b AND b <@ a AND a ? 'k' AND a->>'b' = 'c'"),
+            vec![
+                "select * from t1 where a @> b and b <@ a and a ? 'k' and a->>'b' = 'c';\n"
+                    .to_owned()
+            ]
+        );
+        assert_eq!(
+            format("SELECT * FROM t1 WHERE a ILIKE 'b%' OR a NOT ILIKE 'c%'"),
+            vec!["select * from t1 where a ilike 'b%' or a not ilike 'c%';\n".to_owned()]
+        );
+        assert_eq!(
+            format("SELECT ARRAY[1, 2], ARRAY[ARRAY[a + 1]], ARRAY(SELECT a FROM t1)"),
+            vec!["select array[1, 2], array[array[a + 1]], array(select a from t1);\n".to_owned()]
+        );
+        assert_eq!(
+            format("SELECT DISTINCT ON (a, lower(b)) a, b FROM t1 ORDER BY a"),
+            vec!["select distinct on (a, lower(b)) a, b from t1 order by a;\n".to_owned()]
+        );
+        assert_eq!(
+            format(
+                "INSERT INTO t1 (a, b) VALUES (1, 2) ON CONFLICT (a) DO NOTHING RETURNING a AS c"
+            ),
+            vec![
+                "insert into t1 (a, b) values (1, 2) on conflict (a) do nothing returning a as c;\n"
+                    .to_owned()
+            ]
+        );
+        assert_eq!(
+            format(concat!(
+                "INSERT INTO t1 (a, b) VALUES (1, 2) ON CONFLICT ON CONSTRAINT t1_pkey ",
+                "DO UPDATE SET b = excluded.b WHERE t1.b < excluded.b RETURNING *"
+            )),
+            vec![concat!(
+                "insert into t1 (a, b) values (1, 2)\n",
+                "on conflict on constraint t1_pkey do update set b = excluded.b\n",
+                "where t1.b < excluded.b\n",
+                "returning *;\n"
+            )
+            .to_owned()]
+        );
+        assert_eq!(
+            format("INSERT INTO t1 SELECT a FROM t2 WHERE a IN (SELECT a FROM t3) RETURNING a, b"),
+            vec![
+                "insert into t1 select a from t2 where a in (select a from t3) returning a, b;\n"
+                    .to_owned()
+            ]
+        );
+        // Elsewhere, `distinct on` fails to parse rather than being taken for a function call.
+        assert!(matches!(
+            format_with_options(
+                "SELECT DISTINCT ON (a) a, b FROM t1",
+                false,
+                &FormatOptions::default()
+            ),
+            Err(FormaError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_snowflake() {
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..FormatOptions::default()
+        };
+        let format = |sql_string| format_with_options(sql_string, false, &options).unwrap();
+        // Paths into semi-structured data are kept exactly as written.
+        assert_eq!(
+            format("SELECT v:Name.first::STRING, t1.v:items[0] FROM t1 WHERE v:id = 1"),
+            vec!["select v:Name.first::string, t1.v:items[0] from t1 where v:id = 1;\n".to_owned()]
+        );
+        assert_eq!(
+            format("SELECT f.value FROM t1, LATERAL FLATTEN(input => t1.v, outer => TRUE) AS f"),
+            vec![
+                "select f.value from t1, lateral flatten(input => t1.v, outer => true) as f;\n"
+                    .to_owned()
+            ]
+        );
+        assert_eq!(
+            format(
+                "SELECT a FROM t1 WHERE b ILIKE 'x%' QUALIFY row_number() OVER (ORDER BY b) = 1"
+            ),
+            vec![concat!(
+                "select a from t1 where b ilike 'x%' ",
+                "qualify row_number() over(order by b) = 1;\n"
+            )
+            .to_owned()]
+        );
+        assert_eq!(
+            format(concat!(
+                "SELECT a, count(*) FROM t1 GROUP BY a HAVING count(*) > 1 ",
+                "QUALIFY rank() OVER (ORDER BY count(*)) < 3 ORDER BY a"
+            )),
+            vec![concat!(
+                "select\n",
+                "  a, count(*)\n",
+                "from\n",
+                "  t1\n",
+                "group by\n",
+                "  a\n",
+                "having\n",
+                "  count(*) > 1\n",
+                "qualify\n",
+                "  rank() over(order by count(*)) < 3\n",
+                "order by\n",
+                "  a;\n"
+            )
+            .to_owned()]
+        );
+    }
+
     #[test]
     fn test_format_check() {
         let sql_string = "select * from t1";
         let result = format(sql_string, true, MAX_WIDTH);
         dbg!(&result);
         assert_eq!(result.is_err(), true);
+        // Formatted input passes as a whole, however many statements it holds.
+        assert!(format("select 1;\nselect * from t1;\n", true, MAX_WIDTH).is_ok());
     }
 }
@@ -5,21 +5,37 @@
 //!
 //! The style of formatting is intended to be opinionated and so generally not configurable. The
 //! primary exception is the ability to set a maximum width which the formatter will attempt to
-//! adhere the output to.
+//! adhere the output to. Beyond that, [`format_with_options`] accepts [`FormatOptions`] covering
+//! the handful of conventions, such as indentation, that teams most often cannot bend on.
 //!
-//! Currently `formation` uses a generic SQL dialect that understands templated strings in the form
-//! of `{{ .. }}`. Configurable dialects may be added in the future.
+//! By default `formation` uses a generic SQL dialect that understands templated strings in the form
+//! of `{{ .. }}`. Other dialects may be selected with [`Dialect`], or detected by
+//! [`format_detected`]. Rather than apply formatting, [`unified_diff`] describes the changes it
+//! would make.
 //!
 //! [`format`]: format/fn.format.html
+//! [`format_with_options`]: format/fn.format_with_options.html
+//! [`FormatOptions`]: options/struct.FormatOptions.html
+//! [`Dialect`]: options/enum.Dialect.html
+//! [`format_detected`]: format/fn.format_detected.html
+//! [`unified_diff`]: diff/fn.unified_diff.html
 //! [`forma`]: ../forma/index.html
 
 #![deny(clippy::all, missing_docs)]
 #![feature(box_syntax, box_patterns)]
 
 mod constants;
+pub mod detect;
 mod dialect;
+pub mod diff;
 mod doc;
 pub mod error;
 pub mod format;
+mod mask;
+pub mod options;
+mod procedural;
+mod scan;
+mod split;
+mod template;
 
-pub use crate::format::format;
+pub use crate::format::{format, format_detected, format_with_options};
@@ -0,0 +1,457 @@
+//! Syntax masking
+//!
+//! Some syntax the parser cannot understand may nonetheless be carried through formatting
+//! untouched, such as bind parameter placeholders, e.g. `$1`, `?` or `:name`, template
+//! expressions, e.g. `{{ var }}` or `<%= var %>`, and Snowflake's paths into semi-structured data,
+//! e.g. `v:name.first`. Each occurrence is masked as a sentinel
+//! identifier ahead of parsing. The sentinels are restored, exactly as written, as the text of the
+//! document is built, so that lines are fitted to the maximum width with the original syntax in
+//! place.
+
+use sqlparser::dialect::keywords::Keyword;
+use sqlparser::dialect::Dialect as ParserDialect;
+use sqlparser::tokenizer::{Token, Word};
+
+use crate::dialect::TemplatedDialect;
+use crate::options::{Dialect, TemplateDelimiters, TemplateKind};
+use crate::split;
+use crate::template::{dbt_relation, tag_at};
+
+/// The original text of the masked syntax, in the order it was masked.
+#[derive(Debug)]
+pub struct Masks {
+    /// The start of every sentinel, which appears nowhere in the source SQL.
+    prefix: String,
+    originals: Vec<Original>,
+}
+
+/// The original text of a single piece of masked syntax.
+#[derive(Debug)]
+struct Original {
+    text: String,
+    /// Whether the sentinel was quoted as a string of its own, e.g. `'forma_mask_0_'`, the quotes
+    /// being restored along with it.
+    quoted: bool,
+}
+
+impl Masks {
+    /// Creates masks for the given SQL, whose sentinels cannot be confused with anything written
+    /// within it, in any case.
+    pub fn new(sql: &str) -> Self {
+        let sql = sql.to_lowercase();
+        let mut prefix = "forma_mask_".to_owned();
+        while sql.contains(&prefix) {
+            prefix.push('_');
+        }
+        Masks {
+            prefix,
+            originals: vec![],
+        }
+    }
+
+    /// Masks the template expressions within the given SQL, e.g. `{{ ref('orders') }}` or
+    /// `<%= since %>`, returning the masked SQL.
+    ///
+    /// Expressions are masked wherever they appear, strings included, as the template engine
+    /// renders them regardless.
+    pub fn mask_templates(&mut self, sql: &str, templates: &[TemplateDelimiters]) -> String {
+        let mut masked = String::new();
+        let mut start = 0;
+        let mut position = 0;
+        while position < sql.len() {
+            match tag_at(&sql[position..], templates) {
+                Some((delimiters, TemplateKind::Expression, length)) => {
+                    let tag = &sql[position..position + length];
+                    masked.push_str(&sql[start..position]);
+                    masked.push_str(&self.push(
+                        dbt_relation(tag, delimiters).unwrap_or_else(|| tag.to_owned()),
+                        false,
+                    ));
+                    position += length;
+                    start = position;
+                }
+                _ => {
+                    position += sql[position..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+        }
+        masked.push_str(&sql[start..]);
+        masked
+    }
+
+    /// Masks the strings of the given SQL which MySQL quotes with `"`, returning the masked SQL.
+    /// Each is masked as a single-quoted string, which the parser understands, holding a sentinel.
+    ///
+    /// MySQL's `ANSI_QUOTES` mode, in which `"` quotes identifiers instead, is not supported.
+    pub fn mask_double_quoted_strings(&mut self, sql: &str, dialect: Dialect) -> String {
+        if dialect != Dialect::MySql {
+            return sql.to_owned();
+        }
+        let mut masked = String::new();
+        let mut start = 0;
+        for string in split::double_quoted_strings(sql) {
+            masked.push_str(&sql[start..string.start]);
+            masked.push('\'');
+            masked.push_str(&self.push(sql[string.clone()].to_owned(), true));
+            masked.push('\'');
+            start = string.end;
+        }
+        masked.push_str(&sql[start..]);
+        masked
+    }
+
+    /// Masks the placeholders among the given tokens, returning the masked tokens.
+    ///
+    /// Only the placeholder styles of the given dialect's drivers are masked, and only where an
+    /// operand may appear, such that `a%s` remains a modulo and Snowflake's `v:name` a path.
+    pub fn mask_placeholders(&mut self, tokens: &[Token], dialect: Dialect) -> Vec<Token> {
+        let styles = placeholder_styles(dialect);
+        let mut masked: Vec<Token> = vec![];
+        let mut position = 0;
+        while position < tokens.len() {
+            let previous = masked
+                .iter()
+                .rev()
+                .find(|token| !matches!(token, Token::Whitespace(_)));
+            let operand = !matches!(previous, Some(token) if ends_expression(token));
+            match placeholder(&tokens[position..]) {
+                Some((style, length)) if operand && styles.contains(&style) => {
+                    let original = tokens[position..position + length]
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    masked.push(Token::make_word(&self.push(original, false), None));
+                    position += length;
+                }
+                _ => {
+                    masked.push(tokens[position].clone());
+                    position += 1;
+                }
+            }
+        }
+        masked
+    }
+
+    /// Masks Snowflake's paths into semi-structured data among the given tokens, e.g.
+    /// `v:name.first` or `v:items[0]`, returning the masked tokens. Each path is masked along with
+    /// the column it starts from, which the parser then takes for an identifier, so that it is kept
+    /// exactly as written.
+    pub fn mask_paths(&mut self, tokens: &[Token], dialect: Dialect) -> Vec<Token> {
+        if dialect != Dialect::Snowflake {
+            return tokens.to_vec();
+        }
+        let mut masked = vec![];
+        let mut position = 0;
+        while position < tokens.len() {
+            match path_len(&tokens[position..]) {
+                Some(length) => {
+                    let original = tokens[position..position + length]
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    masked.push(Token::make_word(&self.push(original, false), None));
+                    position += length;
+                }
+                None => {
+                    masked.push(tokens[position].clone());
+                    position += 1;
+                }
+            }
+        }
+        masked
+    }
+
+    /// Records the original text of masked syntax, returning the sentinel which stands in for it.
+    fn push(&mut self, text: String, quoted: bool) -> String {
+        let sentinel = self.sentinel(self.originals.len());
+        self.originals.push(Original { text, quoted });
+        sentinel
+    }
+
+    /// An identifier standing in for masked syntax. The trailing underscore keeps one sentinel
+    /// from being a prefix of another, e.g. `forma_mask_1_` of `forma_mask_10_`.
+    fn sentinel(&self, index: usize) -> String {
+        format!("{}{}_", self.prefix, index)
+    }
+
+    /// Restores the masked syntax within the given text. Syntax is restored in the reverse of the
+    /// order it was masked, as that masked later, e.g. a MySQL string, may hold that masked
+    /// earlier, e.g. a template expression.
+    ///
+    /// Given `lowercase_identifiers`, masked syntax which the templated dialect reads as an
+    /// identifier, e.g. `{{Date}}` or `@Var`, is lowercased, as it was before being masked.
+    pub fn unmask(&self, text: &str, lowercase_identifiers: bool) -> String {
+        if !text.contains(&self.prefix) {
+            return text.to_owned();
+        }
+        self.originals
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(text.to_owned(), |text, (index, original)| {
+                let sentinel = if original.quoted {
+                    format!("'{}'", self.sentinel(index))
+                } else {
+                    self.sentinel(index)
+                };
+                if lowercase_identifiers && is_templated_identifier(&original.text) {
+                    text.replace(&sentinel, &original.text.to_lowercase())
+                } else {
+                    text.replace(&sentinel, &original.text)
+                }
+            })
+    }
+}
+
+/// Whether the templated dialect reads the given text as a single identifier.
+fn is_templated_identifier(text: &str) -> bool {
+    let dialect = TemplatedDialect {};
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(first) if dialect.is_identifier_start(first))
+        && chars.all(|ch| dialect.is_identifier_part(ch))
+}
+
+/// A style of bind parameter placeholder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlaceholderStyle {
+    /// `?` and `?1`, as used by JDBC, ODBC and SQLite.
+    Question,
+    /// `$1`, as used by PostgreSQL.
+    Dollar,
+    /// `:name` and `:1`, as used by Oracle, SQLAlchemy and Snowflake.
+    Colon,
+    /// `@name`, as used by SQL Server and BigQuery.
+    At,
+    /// `%s` and `%(name)s`, as used by Python's database drivers, e.g. psycopg.
+    Percent,
+}
+
+/// Returns the placeholder styles of the drivers of the given dialect. The generic dialects
+/// accept every style, as they are used for queries lifted from any application.
+fn placeholder_styles(dialect: Dialect) -> &'static [PlaceholderStyle] {
+    use PlaceholderStyle::*;
+    match dialect {
+        Dialect::Templated | Dialect::Generic | Dialect::Ansi => {
+            &[Question, Dollar, Colon, At, Percent]
+        }
+        Dialect::PostgreSql => &[Question, Dollar, Colon, Percent],
+        // `@` starts a user variable.
+        Dialect::MySql => &[Question, Colon, Percent],
+        Dialect::MsSql => &[Question, At],
+        Dialect::Sqlite => &[Question, Colon, At],
+        Dialect::BigQuery => &[Question, At],
+        Dialect::Snowflake => &[Question, Colon],
+    }
+}
+
+/// Returns `true` if the given token may end an expression, after which a placeholder cannot
+/// follow, e.g. the `a` of `a%s` or the `v` of `v:name`.
+fn ends_expression(token: &Token) -> bool {
+    match token {
+        Token::Word(Word {
+            keyword,
+            quote_style,
+            ..
+        }) => quote_style.is_some() || *keyword == Keyword::NoKeyword,
+        Token::Number(_)
+        | Token::SingleQuotedString(_)
+        | Token::NationalStringLiteral(_)
+        | Token::HexStringLiteral(_)
+        | Token::RParen
+        | Token::RBracket => true,
+        _ => false,
+    }
+}
+
+/// Returns the style of the placeholder which starts the given tokens, if any, along with the
+/// number of tokens making it up.
+fn placeholder(tokens: &[Token]) -> Option<(PlaceholderStyle, usize)> {
+    use PlaceholderStyle::*;
+    let is_s = |token: &Token| matches!(token, Token::Word(Word { value, .. }) if value == "s");
+    match tokens {
+        [Token::Mod, Token::LParen, Token::Word(_), Token::RParen, s, ..] if is_s(s) => {
+            Some((Percent, 5))
+        }
+        [Token::Mod, s, ..] if is_s(s) => Some((Percent, 2)),
+        [Token::Char('?'), Token::Number(_), ..] => Some((Question, 2)),
+        [Token::Char('?'), ..] => Some((Question, 1)),
+        [Token::Char('$'), Token::Number(_), ..] => Some((Dollar, 2)),
+        [Token::Colon, Token::Word(_), ..] | [Token::Colon, Token::Number(_), ..] => {
+            Some((Colon, 2))
+        }
+        [Token::Char('@'), Token::Word(_), ..] => Some((At, 2)),
+        // Dialects which allow `@` to start an identifier, e.g. `@name`, would otherwise lowercase
+        // the placeholder.
+        [Token::Word(Word {
+            value,
+            quote_style: None,
+            ..
+        }), ..]
+            if value.starts_with('@') =>
+        {
+            Some((At, 1))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the number of tokens making up the path into semi-structured data which starts the
+/// given tokens, if any, e.g. `v:name.first`. The `:` must directly follow the column, such that
+/// `select :name` remains a placeholder, and the path ends ahead of any `::` cast.
+fn path_len(tokens: &[Token]) -> Option<usize> {
+    if !matches!(tokens, [Token::Word(_), Token::Colon, Token::Word(_), ..]) {
+        return None;
+    }
+    let is_index = |token: &Token| matches!(token, Token::Number(_) | Token::SingleQuotedString(_));
+    let mut length = 3;
+    loop {
+        match &tokens[length..] {
+            [Token::Period, Token::Word(_), ..] => length += 2,
+            [Token::LBracket, index, Token::RBracket, ..] if is_index(index) => length += 3,
+            _ => return Some(length),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use sqlparser::tokenizer::Tokenizer;
+
+    use crate::dialect::TemplatedDialect;
+    use crate::scan::to_sql;
+
+    fn mask(sql: &str, dialect: Dialect) -> (Masks, String) {
+        let tokens = Tokenizer::new(&TemplatedDialect {}, sql)
+            .tokenize()
+            .unwrap();
+        let mut masks = Masks::new(sql);
+        let masked = to_sql(&masks.mask_placeholders(&tokens, dialect));
+        (masks, masked)
+    }
+
+    #[test]
+    fn test_mask() {
+        let sql = "select a from t1 where b = ? and c = :Name and d = %(id)s and e = @UserId";
+        let (masks, masked) = mask(sql, Dialect::Templated);
+        assert_eq!(
+            masked,
+            concat!(
+                "select a from t1 where b = forma_mask_0_ and c = forma_mask_1_ ",
+                "and d = forma_mask_2_ and e = forma_mask_3_"
+            )
+        );
+        assert_eq!(masks.unmask(&masked, false), sql);
+    }
+
+    #[test]
+    fn test_mask_operands() {
+        // Placeholders cannot follow an expression.
+        let sql = "select a%s, (b) % s, v:name from t1 where c in (%s, ?)";
+        assert_eq!(
+            mask(sql, Dialect::Templated).1,
+            "select a%s, (b) % s, v:name from t1 where c in (forma_mask_0_, forma_mask_1_)"
+        );
+        // Only the placeholder styles of the dialect are masked.
+        let sql = "select @a, :b, $1";
+        assert_eq!(
+            mask(sql, Dialect::PostgreSql).1,
+            "select @a, forma_mask_0_, forma_mask_1_"
+        );
+        assert_eq!(mask(sql, Dialect::MsSql).1, "select forma_mask_0_, :b, $1");
+    }
+
+    #[test]
+    fn test_mask_sentinels() {
+        // Sentinels never collide with what is written in the SQL.
+        let sql = "select 'forma_mask_0_', FORMA_MASK__1_ where a = ?";
+        let (masks, masked) = mask(sql, Dialect::Templated);
+        assert_eq!(
+            masked,
+            "select 'forma_mask_0_', FORMA_MASK__1_ where a = forma_mask___0_"
+        );
+        assert_eq!(masks.unmask(&masked, false), sql);
+    }
+
+    #[test]
+    fn test_mask_templates() {
+        let sql = "select '{{ x }}' from {{ ref('a}}') }} where {x} = {{y}}";
+        let jinja = TemplateDelimiters::jinja();
+        let mut masks = Masks::new(sql);
+        let masked = masks.mask_templates(sql, &jinja);
+        assert_eq!(
+            masked,
+            "select 'forma_mask_0_' from forma_mask_1_ where {x} = forma_mask_2_"
+        );
+        assert_eq!(masks.unmask(&masked, false), sql);
+        // Only what the templated dialect reads as an identifier is lowercased.
+        let sql = "select {{Y}}, {{ Z }}";
+        let mut masks = Masks::new(sql);
+        let masked = masks.mask_templates(sql, &jinja);
+        assert_eq!(masks.unmask(&masked, true), "select {{y}}, {{ Z }}");
+
+        let sql = "select * from {{ref(\"a\")}}";
+        let mut masks = Masks::new(sql);
+        let masked = masks.mask_templates(sql, &jinja);
+        assert_eq!(masks.unmask(&masked, false), "select * from {{ ref('a') }}");
+
+        let erb = [TemplateDelimiters::new(
+            "<%=",
+            "%>",
+            TemplateKind::Expression,
+        )];
+        let sql = "select * from t1 where d > <%= since %> and e <> 'é'";
+        let mut masks = Masks::new(sql);
+        let masked = masks.mask_templates(sql, &erb);
+        assert_eq!(
+            masked,
+            "select * from t1 where d > forma_mask_0_ and e <> 'é'"
+        );
+        assert_eq!(masks.unmask(&masked, false), sql);
+    }
+
+    #[test]
+    fn test_mask_double_quoted_strings() {
+        let sql = "select \"it's\", '{{ x }}', `a\"b` from t1 where c = \"{{ y }}\"";
+        let mut masks = Masks::new(sql);
+        let masked = masks.mask_templates(sql, &TemplateDelimiters::jinja());
+        let masked = masks.mask_double_quoted_strings(&masked, Dialect::MySql);
+        assert_eq!(
+            masked,
+            concat!(
+                "select 'forma_mask_2_', 'forma_mask_0_', `a\"b` ",
+                "from t1 where c = 'forma_mask_3_'"
+            )
+        );
+        assert_eq!(masks.unmask(&masked, false), sql);
+        // Elsewhere, `"` quotes identifiers.
+        let sql = "select \"a\" from t1";
+        assert_eq!(
+            Masks::new(sql).mask_double_quoted_strings(sql, Dialect::PostgreSql),
+            sql
+        );
+    }
+
+    #[test]
+    fn test_mask_paths() {
+        let sql = "select v:name.first::string, f.value:items[0], :p from t1 where v:\"Id\" = :id";
+        let tokens = Tokenizer::new(&TemplatedDialect {}, sql)
+            .tokenize()
+            .unwrap();
+        let mut masks = Masks::new(sql);
+        let masked = to_sql(&masks.mask_paths(&tokens, Dialect::Snowflake));
+        assert_eq!(
+            masked,
+            concat!(
+                "select forma_mask_0_::string, f.forma_mask_1_, :p ",
+                "from t1 where forma_mask_2_ = :id"
+            )
+        );
+        assert_eq!(masks.unmask(&masked, false), sql);
+        assert_eq!(
+            to_sql(&Masks::new(sql).mask_paths(&tokens, Dialect::Generic)),
+            sql
+        );
+    }
+}
@@ -0,0 +1,475 @@
+//! Formatting options
+//!
+//! Provides [`FormatOptions`], the set of knobs the formatter consults while laying out SQL. The
+//! defaults reproduce `formation`'s opinionated style; the options exist for teams whose existing
+//! conventions differ in ways the formatter cannot reasonably paper over.
+//!
+//! Rather than setting each option by hand, a named [`Style`] provides a complete set of options
+//! which individual options may then override.
+//!
+//! With the `serde` feature enabled, the option types may be deserialized, e.g. from a
+//! configuration file.
+//!
+//! [`FormatOptions`]: struct.FormatOptions.html
+//! [`Style`]: enum.Style.html
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::constants::{
+    DEFAULT_INDENT_WIDTH, DEFAULT_MAX_WIDTH, ENTERPRISE_INDENT_WIDTH, RIVER_WIDTH,
+};
+use crate::error::FormaError;
+
+/// How a single level of indentation is written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum IndentStyle {
+    /// Indent with `indent_width` spaces per level.
+    Spaces,
+    /// Indent with one tab per level. When fitting lines to `max_width`, a tab counts as
+    /// `indent_width` columns.
+    Tabs,
+}
+
+/// Where the comma separating list items is placed when a list breaks across lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum CommaPosition {
+    /// Commas end each line, e.g. `col_a,`.
+    Trailing,
+    /// Commas lead each line after the first, e.g. `, col_b`.
+    Leading,
+}
+
+impl FromStr for CommaPosition {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trailing" => Ok(CommaPosition::Trailing),
+            "leading" => Ok(CommaPosition::Leading),
+            _ => Err(FormaError::InvalidOption {
+                option: "comma_position",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
+
+/// How clause keywords and their bodies are arranged relative to one another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Layout {
+    /// Clause keywords sit on lines of their own with their bodies indented beneath them.
+    Standard,
+    /// Clause keywords are right-aligned so that their bodies share a single column, the
+    /// "river", as described by the [SQL style guide](https://www.sqlstyle.guide/#spaces).
+    River,
+}
+
+impl FromStr for Layout {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Layout::Standard),
+            "river" => Ok(Layout::River),
+            _ => Err(FormaError::InvalidOption {
+                option: "layout",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Whether aliases are introduced with the `as` keyword.
+///
+/// Whatever the style, `as` is kept wherever leaving it out could change how the SQL parses,
+/// e.g. before an alias that is itself a keyword.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum AliasStyle {
+    /// Always write `as`, e.g. `count(*) as total`.
+    Explicit,
+    /// Never write `as`, e.g. `count(*) total`.
+    Implicit,
+    /// Write `as` only where the original SQL did.
+    Preserve,
+}
+
+impl FromStr for AliasStyle {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "explicit" => Ok(AliasStyle::Explicit),
+            "implicit" => Ok(AliasStyle::Implicit),
+            "preserve" => Ok(AliasStyle::Preserve),
+            _ => Err(FormaError::InvalidOption {
+                option: "alias_style",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
+
+/// The case keywords are written in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum KeywordCase {
+    /// Lowercase keywords, e.g. `select`.
+    Lower,
+    /// Uppercase keywords, e.g. `SELECT`.
+    Upper,
+}
+
+impl KeywordCase {
+    /// Returns the given keyword written in this case.
+    pub(crate) fn apply(self, keyword: &str) -> String {
+        match self {
+            KeywordCase::Lower => keyword.to_lowercase(),
+            KeywordCase::Upper => keyword.to_uppercase(),
+        }
+    }
+}
+
+impl FromStr for KeywordCase {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lower" => Ok(KeywordCase::Lower),
+            "upper" => Ok(KeywordCase::Upper),
+            _ => Err(FormaError::InvalidOption {
+                option: "keyword_case",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
+
+/// The SQL dialect input is parsed as, and rendered for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Dialect {
+    /// A generic dialect which also accepts templated identifiers, e.g. `{{date}}`.
+    Templated,
+    /// The generic dialect.
+    Generic,
+    /// ANSI SQL.
+    Ansi,
+    /// PostgreSQL.
+    ///
+    /// Keeps `x::type` casts as written and understands JSON operators such as `->>` and `@>`,
+    /// `ARRAY[...]`, `ILIKE`, `DISTINCT ON`, `ON CONFLICT` and `RETURNING`.
+    PostgreSql,
+    /// MySQL.
+    MySql,
+    /// Microsoft SQL Server, i.e. T-SQL.
+    MsSql,
+    /// SQLite.
+    Sqlite,
+    /// Google BigQuery.
+    ///
+    /// Accepts backtick-quoted paths and understands `STRUCT<...>` and `ARRAY<...>` types and
+    /// constructors, `UNNEST(...) WITH OFFSET`, `SAFE_CAST` and `SELECT * EXCEPT (...)` and
+    /// `REPLACE (...)`.
+    BigQuery,
+    /// Snowflake.
+    ///
+    /// Keeps `x::type` casts as written and understands `QUALIFY`, `LATERAL FLATTEN(...)`, named
+    /// arguments (`=>`), `ILIKE` and semi-structured paths such as `v:name`, which are kept as
+    /// written.
+    Snowflake,
+}
+
+impl Dialect {
+    /// Every dialect, in the order they are tried when the dialect of some SQL is unknown.
+    pub const ALL: [Dialect; 9] = [
+        Dialect::Templated,
+        Dialect::Generic,
+        Dialect::PostgreSql,
+        Dialect::MySql,
+        Dialect::MsSql,
+        Dialect::BigQuery,
+        Dialect::Snowflake,
+        Dialect::Sqlite,
+        Dialect::Ansi,
+    ];
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dialect::Templated => "templated",
+            Dialect::Generic => "generic",
+            Dialect::Ansi => "ansi",
+            Dialect::PostgreSql => "postgresql",
+            Dialect::MySql => "mysql",
+            Dialect::MsSql => "mssql",
+            Dialect::Sqlite => "sqlite",
+            Dialect::BigQuery => "bigquery",
+            Dialect::Snowflake => "snowflake",
+        })
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "templated" => Ok(Dialect::Templated),
+            "generic" => Ok(Dialect::Generic),
+            "ansi" => Ok(Dialect::Ansi),
+            "postgresql" => Ok(Dialect::PostgreSql),
+            "mysql" => Ok(Dialect::MySql),
+            "mssql" => Ok(Dialect::MsSql),
+            "sqlite" => Ok(Dialect::Sqlite),
+            "bigquery" => Ok(Dialect::BigQuery),
+            "snowflake" => Ok(Dialect::Snowflake),
+            _ => Err(FormaError::InvalidOption {
+                option: "dialect",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
+
+/// How the span between a pair of template delimiters is treated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TemplateKind {
+    /// An expression standing in for an identifier or value, e.g. `{{ var }}`.
+    Expression,
+    /// A statement, e.g. `{% if x %}`, kept on a line of its own between SQL statements.
+    Statement,
+    /// A comment, e.g. `{# note #}`, kept like a statement.
+    Comment,
+}
+
+/// A pair of delimiters enclosing a span of a template language, e.g. `{{` and `}}`.
+///
+/// Spans are kept exactly as written, so any template language may be formatted given its
+/// delimiters, e.g. ERB's `<%=` and `%>`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct TemplateDelimiters {
+    /// The delimiter opening a span.
+    pub open: String,
+    /// The delimiter closing a span.
+    pub close: String,
+    /// How the span is treated.
+    pub kind: TemplateKind,
+}
+
+impl TemplateDelimiters {
+    /// Creates a pair of delimiters enclosing spans of the given kind.
+    pub fn new(open: &str, close: &str, kind: TemplateKind) -> Self {
+        TemplateDelimiters {
+            open: open.to_owned(),
+            close: close.to_owned(),
+            kind,
+        }
+    }
+
+    /// Returns the delimiters of Jinja, which are shared by the likes of Go templates, Mustache
+    /// and Tera.
+    pub fn jinja() -> Vec<TemplateDelimiters> {
+        vec![
+            TemplateDelimiters::new("{{", "}}", TemplateKind::Expression),
+            TemplateDelimiters::new("{%", "%}", TemplateKind::Statement),
+            TemplateDelimiters::new("{#", "#}", TemplateKind::Comment),
+        ]
+    }
+}
+
+/// A frozen set of layout behaviors.
+///
+/// Releases which change how SQL is laid out do so under a new edition, leaving output under
+/// earlier editions as it was. Pinning an edition therefore keeps upgrades from reformatting
+/// existing SQL; new layout only takes effect once the edition is raised.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub enum StyleEdition {
+    /// The layout of `formation` 0.3.
+    #[cfg_attr(feature = "serde", serde(rename = "0.3"))]
+    V0_3,
+    /// The layout of `formation` 0.4, in which the templated dialect keeps the case of template
+    /// expressions and placeholders, e.g. `{{Date}}` or `@Var`, rather than lowercasing them as
+    /// identifiers, and `format_detected` detects the dialect of its input.
+    #[cfg_attr(feature = "serde", serde(rename = "0.4"))]
+    V0_4,
+}
+
+impl FromStr for StyleEdition {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0.3" => Ok(StyleEdition::V0_3),
+            "0.4" => Ok(StyleEdition::V0_4),
+            _ => Err(FormaError::InvalidOption {
+                option: "style_edition",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Options controlling how SQL is laid out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// The maximum column width the formatter will attempt to adhere the output to.
+    pub max_width: usize,
+    /// The number of columns a single level of indentation occupies.
+    pub indent_width: usize,
+    /// Whether indentation is written with spaces or tabs.
+    pub indent_style: IndentStyle,
+    /// Where commas are placed in comma-separated lists that break across lines.
+    pub comma_position: CommaPosition,
+    /// How clause keywords and their bodies are arranged.
+    pub layout: Layout,
+    /// Whether column aliases in projections are introduced with `as`.
+    pub column_alias: AliasStyle,
+    /// Whether table aliases are introduced with `as`.
+    pub table_alias: AliasStyle,
+    /// The case keywords are written in.
+    pub keyword_case: KeywordCase,
+    /// The edition of layout behaviors to adhere to.
+    pub style_edition: StyleEdition,
+    /// The dialect input is parsed as, which also determines how identifiers are rendered.
+    pub dialect: Dialect,
+    /// The delimiters of the template language input may be written in, if any.
+    pub templates: Vec<TemplateDelimiters>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            max_width: DEFAULT_MAX_WIDTH,
+            indent_width: DEFAULT_INDENT_WIDTH,
+            indent_style: IndentStyle::Spaces,
+            comma_position: CommaPosition::Trailing,
+            layout: Layout::Standard,
+            column_alias: AliasStyle::Explicit,
+            table_alias: AliasStyle::Explicit,
+            keyword_case: KeywordCase::Lower,
+            style_edition: StyleEdition::V0_3,
+            dialect: Dialect::Templated,
+            templates: TemplateDelimiters::jinja(),
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Returns an error if the options cannot be used together.
+    ///
+    /// Tab indentation cannot be combined with the river layout, as the river is aligned to
+    /// columns which levels of indentation do not fall on.
+    pub fn validate(&self) -> Result<(), FormaError> {
+        if self.indent_style == IndentStyle::Tabs && self.layout == Layout::River {
+            return Err(FormaError::ConflictingOptions(
+                "indent_style = tabs",
+                "layout = river",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the nesting offset of a single level of indentation.
+    pub(crate) fn nest_factor(&self) -> isize {
+        self.indent_width as isize
+    }
+
+    /// Returns the nesting offset of clause bodies, i.e. the column they start in relative to
+    /// their clause keyword.
+    pub(crate) fn clause_nest(&self) -> isize {
+        match self.layout {
+            Layout::Standard => self.nest_factor(),
+            Layout::River => RIVER_WIDTH as isize + 1,
+        }
+    }
+}
+
+/// A named preset of formatting options.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Style {
+    /// `formation`'s own style, i.e. `FormatOptions::default()`.
+    Forma,
+    /// The style described by the [SQL style guide](https://www.sqlstyle.guide): uppercase
+    /// keywords right-aligned along a river.
+    Sqlstyle,
+    /// Uppercase keywords, leading commas and four-space indentation.
+    Enterprise,
+}
+
+impl Style {
+    /// Returns the complete set of options this style stands for.
+    pub fn options(self) -> FormatOptions {
+        match self {
+            Style::Forma => FormatOptions::default(),
+            Style::Sqlstyle => FormatOptions {
+                layout: Layout::River,
+                keyword_case: KeywordCase::Upper,
+                ..FormatOptions::default()
+            },
+            Style::Enterprise => FormatOptions {
+                indent_width: ENTERPRISE_INDENT_WIDTH,
+                comma_position: CommaPosition::Leading,
+                keyword_case: KeywordCase::Upper,
+                ..FormatOptions::default()
+            },
+        }
+    }
+}
+
+impl FromStr for Style {
+    type Err = FormaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forma" => Ok(Style::Forma),
+            "sqlstyle" => Ok(Style::Sqlstyle),
+            "enterprise" => Ok(Style::Enterprise),
+            _ => Err(FormaError::InvalidOption {
+                option: "style",
+                value: s.to_owned(),
+            }),
+        }
+    }
+}
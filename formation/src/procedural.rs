@@ -0,0 +1,620 @@
+//! Procedural statements
+//!
+//! Stored procedures are written in procedural extensions of SQL, such as T-SQL and PL/pgSQL,
+//! whose control flow the parser does not understand. This module parses that control flow on top
+//! of the parser, i.e. `begin ... end` blocks, `if`, `while`, `declare`, assignments and `return`,
+//! handing the SQL statements within to the parser as usual.
+
+use sqlparser::ast::{Assignment, DataType, Expr, Ident, SelectItem, Statement};
+use sqlparser::dialect::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Word};
+
+/// How a block was written, which decides how it is closed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockStyle {
+    /// PL/pgSQL's `if ... then ... end if` and `while ... loop ... end loop`.
+    PlPgSql,
+    /// T-SQL's `if ...` and `while ...`, each followed by a single statement or `begin ... end`.
+    TSql,
+}
+
+/// A variable declaration, e.g. `@x int = 1`.
+#[derive(Debug, PartialEq)]
+pub struct Declaration {
+    /// The name of the variable.
+    pub name: Ident,
+    /// The type of the variable.
+    pub data_type: DataType,
+    /// The initial value of the variable, if any.
+    pub default: Option<Expr>,
+}
+
+/// The constraint a PostgreSQL `on conflict` clause applies to.
+#[derive(Debug, PartialEq)]
+pub enum ConflictTarget {
+    /// The columns of a unique index, e.g. `(a, b)`.
+    Columns(Vec<Ident>),
+    /// A named constraint, e.g. `on constraint t1_pkey`.
+    Constraint(Ident),
+}
+
+/// What an `insert` does with a row which conflicts with one already in the table.
+#[derive(Debug, PartialEq)]
+pub enum OnConflict {
+    /// MySQL's `on duplicate key update`, giving the assignments made to the existing row.
+    DuplicateKeyUpdate(Vec<Assignment>),
+    /// PostgreSQL's `on conflict ... do nothing`.
+    DoNothing(Option<ConflictTarget>),
+    /// PostgreSQL's `on conflict ... do update`.
+    DoUpdate {
+        /// The constraint the row conflicts on, if given.
+        target: Option<ConflictTarget>,
+        /// The assignments made to the existing row.
+        assignments: Vec<Assignment>,
+        /// The condition the existing row must meet to be updated, if any.
+        selection: Option<Expr>,
+    },
+}
+
+/// A statement of a procedural extension of SQL.
+#[derive(Debug, PartialEq)]
+pub enum Block {
+    /// A plain SQL statement.
+    Statement(Statement),
+    /// A `begin ... end` block, along with the variables of a PL/pgSQL `declare` section ahead of
+    /// it.
+    Begin {
+        /// The variables declared ahead of the block.
+        declarations: Vec<Declaration>,
+        /// The statements of the block.
+        body: Vec<Block>,
+    },
+    /// An `if` statement. PL/pgSQL gives each `elsif` as a further branch, where T-SQL nests
+    /// `else if` within the `else` branch.
+    If {
+        /// How the statement was written.
+        style: BlockStyle,
+        /// Each condition along with the statements run when it holds.
+        branches: Vec<(Expr, Vec<Block>)>,
+        /// The statements of the `else` branch, if any.
+        otherwise: Option<Vec<Block>>,
+    },
+    /// A `while` loop.
+    While {
+        /// How the loop was written.
+        style: BlockStyle,
+        /// The condition of the loop.
+        condition: Expr,
+        /// The statements of the loop.
+        body: Vec<Block>,
+    },
+    /// A T-SQL `declare` statement.
+    Declare(Vec<Declaration>),
+    /// A PL/pgSQL assignment, e.g. `x := 1`.
+    Assign {
+        /// The variable assigned to.
+        variable: Ident,
+        /// The value assigned.
+        value: Expr,
+    },
+    /// A `set` statement assigning an expression, e.g. `set @x = @x + 1`.
+    Set {
+        /// The variable assigned to.
+        variable: Ident,
+        /// The value assigned.
+        value: Expr,
+    },
+    /// A `return` statement.
+    Return(Option<Expr>),
+    /// An `insert` followed by clauses the parser does not understand, i.e. MySQL's
+    /// `on duplicate key update` or PostgreSQL's `on conflict` and `returning`.
+    Insert {
+        /// The `insert` statement.
+        insert: Statement,
+        /// What is done with a row which conflicts with one already in the table, if anything.
+        on_conflict: Option<Box<OnConflict>>,
+        /// The items returned for each row inserted, if any.
+        returning: Vec<SelectItem>,
+    },
+}
+
+/// Parses the given tokens as a list of statements, procedural or otherwise.
+///
+/// `sections` selects PL/pgSQL's `declare` sections, which run up to a `begin`, over T-SQL's
+/// `declare` statements.
+pub fn parse_blocks(tokens: Vec<Token>, sections: bool) -> Result<Vec<Block>, ParserError> {
+    parse_list(tokens, false, |parser| parse_block(parser, sections))
+}
+
+/// Parses the given tokens as a list of plain SQL statements, for dialects without a procedural
+/// extension. Each statement must be delimited.
+///
+/// `upserts` accepts MySQL's `insert ... on duplicate key update`.
+pub fn parse_statements(tokens: Vec<Token>, upserts: bool) -> Result<Vec<Block>, ParserError> {
+    parse_list(tokens, true, |parser| {
+        if parse_word(parser, "SET") {
+            return parse_set(parser);
+        }
+        let statement = parser.parse_statement()?;
+        if upserts && matches!(statement, Statement::Insert { .. }) && parse_word(parser, "ON") {
+            for word in &["DUPLICATE", "KEY", "UPDATE"] {
+                expect_word(parser, word)?;
+            }
+            let assignments = parser.parse_comma_separated(Parser::parse_assignment)?;
+            Ok(Block::Insert {
+                insert: statement,
+                on_conflict: Some(Box::new(OnConflict::DuplicateKeyUpdate(assignments))),
+                returning: vec![],
+            })
+        } else {
+            Ok(Block::Statement(statement))
+        }
+    })
+}
+
+fn parse_list(
+    tokens: Vec<Token>,
+    delimited: bool,
+    mut parse: impl FnMut(&mut Parser) -> Result<Block, ParserError>,
+) -> Result<Vec<Block>, ParserError> {
+    let mut parser = Parser::new(tokens);
+    let mut blocks = vec![];
+    let mut expecting_delimiter = false;
+    loop {
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_delimiter = false;
+        }
+        if parser.peek_token() == Token::EOF {
+            break;
+        } else if expecting_delimiter {
+            return expected("end of statement", parser.peek_token());
+        }
+        let block = parse(&mut parser)?;
+        // Procedural statements need not be delimited, as is common in T-SQL.
+        expecting_delimiter = delimited || matches!(block, Block::Statement(_));
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+fn expected<T>(expected: &str, found: Token) -> Result<T, ParserError> {
+    Err(ParserError::ParserError(format!(
+        "Expected {}, found: {}",
+        expected, found
+    )))
+}
+
+/// Returns `true` if the next token is the given unquoted word, in any case.
+///
+/// Words are compared by value as not every word of a procedural language is a keyword of the
+/// parser.
+fn peek_word(parser: &Parser, word: &str) -> bool {
+    matches!(
+        parser.peek_token(),
+        Token::Word(Word { value, quote_style: None, .. }) if value.eq_ignore_ascii_case(word)
+    )
+}
+
+fn parse_word(parser: &mut Parser, word: &str) -> bool {
+    if peek_word(parser, word) {
+        parser.next_token();
+        true
+    } else {
+        false
+    }
+}
+
+fn expect_word(parser: &mut Parser, word: &str) -> Result<(), ParserError> {
+    if parse_word(parser, word) {
+        Ok(())
+    } else {
+        expected(word, parser.peek_token())
+    }
+}
+
+fn parse_block(parser: &mut Parser, sections: bool) -> Result<Block, ParserError> {
+    if parse_word(parser, "BEGIN") {
+        // `begin` alone, or followed by `transaction`, starts a transaction rather than a block.
+        if matches!(parser.peek_token(), Token::EOF | Token::SemiColon)
+            || ["TRANSACTION", "TRAN", "WORK"]
+                .iter()
+                .any(|word| peek_word(parser, word))
+        {
+            parser.prev_token();
+            Ok(Block::Statement(parse_statement(parser)?))
+        } else {
+            parse_begin(parser, vec![], sections)
+        }
+    } else if parse_word(parser, "DECLARE") {
+        parse_declare(parser, sections)
+    } else if parse_word(parser, "IF") {
+        parse_if(parser, sections)
+    } else if parse_word(parser, "WHILE") {
+        parse_while(parser, sections)
+    } else if parse_word(parser, "RETURN") {
+        if matches!(parser.peek_token(), Token::EOF | Token::SemiColon) || peek_word(parser, "END")
+        {
+            Ok(Block::Return(None))
+        } else {
+            Ok(Block::Return(Some(parser.parse_expr()?)))
+        }
+    } else if parse_word(parser, "SET") {
+        parse_set(parser)
+    } else {
+        match parser.next_token() {
+            Token::Word(Word {
+                value, quote_style, ..
+            }) if parser.consume_token(&Token::Colon) => {
+                parser.expect_token(&Token::Eq)?;
+                Ok(Block::Assign {
+                    variable: Ident { value, quote_style },
+                    value: parser.parse_expr()?,
+                })
+            }
+            _ => {
+                parser.prev_token();
+                let statement = parse_statement(parser)?;
+                if matches!(statement, Statement::Insert { .. })
+                    && (peek_word(parser, "ON") || peek_word(parser, "RETURNING"))
+                {
+                    parse_insert(parser, statement)
+                } else {
+                    Ok(Block::Statement(statement))
+                }
+            }
+        }
+    }
+}
+
+/// Parses a plain SQL statement within a procedural one.
+///
+/// The statement ends ahead of an unquoted `else` or `end` outside of any `case`, as T-SQL's need
+/// not be delimited, e.g. `if x select 1 else select 2`. The parser would otherwise take either
+/// word for an alias. It also ends ahead of PostgreSQL's `on conflict` and `returning` outside of
+/// any parentheses, which `parse_insert` takes.
+fn parse_statement(parser: &mut Parser) -> Result<Statement, ParserError> {
+    let mut tokens = vec![];
+    let mut cases = 0;
+    let mut depth = 0;
+    loop {
+        let token = parser.next_token();
+        match &token {
+            Token::EOF | Token::SemiColon => break,
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::Word(Word {
+                value,
+                keyword,
+                quote_style: None,
+            }) => match keyword {
+                Keyword::CASE => cases += 1,
+                Keyword::ELSE | Keyword::END if cases == 0 => break,
+                Keyword::END => cases -= 1,
+                Keyword::ON if depth == 0 && peek_word(parser, "CONFLICT") => break,
+                _ if depth == 0 && value.eq_ignore_ascii_case("RETURNING") => break,
+                _ => {}
+            },
+            _ => {}
+        }
+        tokens.push(token);
+    }
+    for _ in 0..=tokens.len() {
+        parser.prev_token();
+    }
+    // The statement is parsed from its own tokens, and only those it took are consumed, as
+    // undelimited statements may follow it.
+    let length = tokens.len();
+    let mut statement_parser = Parser::new(tokens);
+    let statement = statement_parser.parse_statement()?;
+    let mut rest = 0;
+    while statement_parser.next_token() != Token::EOF {
+        rest += 1;
+    }
+    for _ in rest..length {
+        parser.next_token();
+    }
+    Ok(statement)
+}
+
+/// Parses PostgreSQL's `on conflict` and `returning` clauses following an `insert`.
+fn parse_insert(parser: &mut Parser, insert: Statement) -> Result<Block, ParserError> {
+    let on_conflict = if parse_word(parser, "ON") {
+        expect_word(parser, "CONFLICT")?;
+        let target = if parser.consume_token(&Token::LParen) {
+            let columns = parser.parse_comma_separated(Parser::parse_identifier)?;
+            parser.expect_token(&Token::RParen)?;
+            Some(ConflictTarget::Columns(columns))
+        } else if parse_word(parser, "ON") {
+            expect_word(parser, "CONSTRAINT")?;
+            Some(ConflictTarget::Constraint(parser.parse_identifier()?))
+        } else {
+            None
+        };
+        expect_word(parser, "DO")?;
+        if parse_word(parser, "NOTHING") {
+            Some(Box::new(OnConflict::DoNothing(target)))
+        } else {
+            expect_word(parser, "UPDATE")?;
+            expect_word(parser, "SET")?;
+            let assignments = parser.parse_comma_separated(Parser::parse_assignment)?;
+            let selection = if parse_word(parser, "WHERE") {
+                Some(parser.parse_expr()?)
+            } else {
+                None
+            };
+            Some(Box::new(OnConflict::DoUpdate {
+                target,
+                assignments,
+                selection,
+            }))
+        }
+    } else {
+        None
+    };
+    let returning = if parse_word(parser, "RETURNING") {
+        parser.parse_comma_separated(Parser::parse_select_item)?
+    } else {
+        vec![]
+    };
+    Ok(Block::Insert {
+        insert,
+        on_conflict,
+        returning,
+    })
+}
+
+/// Parses a `set` statement following its `set`.
+///
+/// Only `set x = ...` is taken here, as the parser accepts no more than a literal or a name as the
+/// value; the parser handles the other forms.
+fn parse_set(parser: &mut Parser) -> Result<Block, ParserError> {
+    match parser.next_token() {
+        Token::Word(Word {
+            value, quote_style, ..
+        }) if parser.consume_token(&Token::Eq) => Ok(Block::Set {
+            variable: Ident { value, quote_style },
+            value: parser.parse_expr()?,
+        }),
+        _ => {
+            parser.prev_token();
+            parser.prev_token();
+            Ok(Block::Statement(parse_statement(parser)?))
+        }
+    }
+}
+
+/// Parses statements up to, but excluding, any of the given words.
+fn parse_body(
+    parser: &mut Parser,
+    sections: bool,
+    terminators: &[&str],
+) -> Result<Vec<Block>, ParserError> {
+    let mut body = vec![];
+    loop {
+        while parser.consume_token(&Token::SemiColon) {}
+        if parser.peek_token() == Token::EOF
+            || terminators.iter().any(|word| peek_word(parser, word))
+        {
+            return Ok(body);
+        }
+        body.push(parse_block(parser, sections)?);
+    }
+}
+
+fn parse_begin(
+    parser: &mut Parser,
+    declarations: Vec<Declaration>,
+    sections: bool,
+) -> Result<Block, ParserError> {
+    let body = parse_body(parser, sections, &["END"])?;
+    expect_word(parser, "END")?;
+    Ok(Block::Begin { declarations, body })
+}
+
+fn parse_declare(parser: &mut Parser, sections: bool) -> Result<Block, ParserError> {
+    if sections {
+        let mut declarations = vec![];
+        while !parse_word(parser, "BEGIN") {
+            declarations.push(parse_declaration(parser)?);
+            parser.expect_token(&Token::SemiColon)?;
+        }
+        parse_begin(parser, declarations, sections)
+    } else {
+        let mut declarations = vec![parse_declaration(parser)?];
+        while parser.consume_token(&Token::Comma) {
+            declarations.push(parse_declaration(parser)?);
+        }
+        Ok(Block::Declare(declarations))
+    }
+}
+
+fn parse_declaration(parser: &mut Parser) -> Result<Declaration, ParserError> {
+    let name = parser.parse_identifier()?;
+    parse_word(parser, "AS");
+    let data_type = parser.parse_data_type()?;
+    let default = if parser.consume_token(&Token::Eq) || parse_word(parser, "DEFAULT") {
+        Some(parser.parse_expr()?)
+    } else if parser.consume_token(&Token::Colon) {
+        parser.expect_token(&Token::Eq)?;
+        Some(parser.parse_expr()?)
+    } else {
+        None
+    };
+    Ok(Declaration {
+        name,
+        data_type,
+        default,
+    })
+}
+
+fn parse_if(parser: &mut Parser, sections: bool) -> Result<Block, ParserError> {
+    let condition = parser.parse_expr()?;
+    if parse_word(parser, "THEN") {
+        let terminators = ["ELSIF", "ELSEIF", "ELSE", "END"];
+        let mut branches = vec![(condition, parse_body(parser, sections, &terminators)?)];
+        while parse_word(parser, "ELSIF") || parse_word(parser, "ELSEIF") {
+            let condition = parser.parse_expr()?;
+            expect_word(parser, "THEN")?;
+            branches.push((condition, parse_body(parser, sections, &terminators)?));
+        }
+        let otherwise = if parse_word(parser, "ELSE") {
+            Some(parse_body(parser, sections, &["END"])?)
+        } else {
+            None
+        };
+        expect_word(parser, "END")?;
+        expect_word(parser, "IF")?;
+        Ok(Block::If {
+            style: BlockStyle::PlPgSql,
+            branches,
+            otherwise,
+        })
+    } else {
+        let body = vec![parse_block(parser, sections)?];
+        let _ = parser.consume_token(&Token::SemiColon);
+        let otherwise = if parse_word(parser, "ELSE") {
+            Some(vec![parse_block(parser, sections)?])
+        } else {
+            None
+        };
+        Ok(Block::If {
+            style: BlockStyle::TSql,
+            branches: vec![(condition, body)],
+            otherwise,
+        })
+    }
+}
+
+fn parse_while(parser: &mut Parser, sections: bool) -> Result<Block, ParserError> {
+    let condition = parser.parse_expr()?;
+    if parse_word(parser, "LOOP") {
+        let body = parse_body(parser, sections, &["END"])?;
+        expect_word(parser, "END")?;
+        expect_word(parser, "LOOP")?;
+        Ok(Block::While {
+            style: BlockStyle::PlPgSql,
+            condition,
+            body,
+        })
+    } else {
+        Ok(Block::While {
+            style: BlockStyle::TSql,
+            condition,
+            body: vec![parse_block(parser, sections)?],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use sqlparser::ast::{BinaryOperator, Value};
+    use sqlparser::tokenizer::Tokenizer;
+
+    use crate::dialect::TemplatedDialect;
+
+    fn parse(sql: &str, sections: bool) -> Vec<Block> {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(&dialect, sql).tokenize().unwrap();
+        parse_blocks(tokens, sections).unwrap()
+    }
+
+    fn ident(value: &str) -> Ident {
+        Ident {
+            value: value.to_owned(),
+            quote_style: None,
+        }
+    }
+
+    fn number(n: &str) -> Expr {
+        Expr::Value(Value::Number(n.to_owned()))
+    }
+
+    #[test]
+    fn test_parse_blocks() {
+        assert_eq!(
+            parse("declare i int = 0 while i < 3 set i = 1", false),
+            vec![
+                Block::Declare(vec![Declaration {
+                    name: ident("i"),
+                    data_type: DataType::Int,
+                    default: Some(number("0")),
+                }]),
+                Block::While {
+                    style: BlockStyle::TSql,
+                    condition: Expr::BinaryOp {
+                        left: Box::new(Expr::Identifier(ident("i"))),
+                        op: BinaryOperator::Lt,
+                        right: Box::new(number("3")),
+                    },
+                    body: vec![Block::Set {
+                        variable: ident("i"),
+                        value: number("1"),
+                    }],
+                },
+            ]
+        );
+        assert_eq!(
+            parse(
+                "declare x int; begin if x then x := 1; elsif 1 then return; end if; end;",
+                true
+            ),
+            vec![Block::Begin {
+                declarations: vec![Declaration {
+                    name: ident("x"),
+                    data_type: DataType::Int,
+                    default: None,
+                }],
+                body: vec![Block::If {
+                    style: BlockStyle::PlPgSql,
+                    branches: vec![
+                        (
+                            Expr::Identifier(ident("x")),
+                            vec![Block::Assign {
+                                variable: ident("x"),
+                                value: number("1"),
+                            }]
+                        ),
+                        (number("1"), vec![Block::Return(None)]),
+                    ],
+                    otherwise: None,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_insert() {
+        match parse(
+            "insert into t1 values (1) on conflict (a) do update set a = 2 returning a",
+            false,
+        )
+        .as_slice()
+        {
+            [Block::Insert {
+                insert: Statement::Insert { .. },
+                on_conflict,
+                returning,
+            }] => {
+                assert_eq!(
+                    on_conflict,
+                    &Some(Box::new(OnConflict::DoUpdate {
+                        target: Some(ConflictTarget::Columns(vec![ident("a")])),
+                        assignments: vec![Assignment {
+                            id: ident("a"),
+                            value: number("2"),
+                        }],
+                        selection: None,
+                    }))
+                );
+                assert_eq!(
+                    returning,
+                    &vec![SelectItem::UnnamedExpr(Expr::Identifier(ident("a")))]
+                );
+            }
+            blocks => panic!("{:?}", blocks),
+        }
+    }
+}
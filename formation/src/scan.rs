@@ -0,0 +1,1022 @@
+//! Source scanning
+//!
+//! The parser discards some of the choices made by the original SQL, such as whether an alias was
+//! introduced with `as`, how a cast was written or which form of `limit` was used. This module
+//! recovers them from the token stream so that they may be respected when rendering.
+//!
+//! Where the parser would lose track of which part of the SQL a choice belongs to, the choice is
+//! marked within the tokens themselves, e.g. an alias introduced with `as` is renamed with a
+//! prefix which the source SQL does not contain. The parser carries the mark through to the alias,
+//! from which it is removed when rendering.
+
+use sqlparser::ast::{DataType, Expr, Ident, ObjectName, Value};
+use sqlparser::dialect::keywords::{Keyword, RESERVED_FOR_TABLE_ALIAS};
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Whitespace, Word};
+
+use crate::options::Dialect;
+
+/// How a cast was written in the original SQL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CastStyle {
+    /// The standard `cast(x as int)` form.
+    Function,
+    /// PostgreSQL's `x::int` shorthand.
+    DoubleColon,
+}
+
+/// How a `limit` with an offset was written in the original SQL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LimitStyle {
+    /// The standard `limit 10 offset 20` form.
+    Standard,
+    /// MySQL's `limit 20, 10` form, giving the offset ahead of the count.
+    Comma,
+}
+
+/// Syntax the parser does not understand, which is marked as a call of a function named for it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    /// `array[...]`, given the elements of the array.
+    Array,
+    /// `array(...)`, given the subquery.
+    ArraySubquery,
+    /// PostgreSQL's `distinct on (...)`, given the expressions rows are distinct on. It is marked
+    /// as the first item of the projection.
+    DistinctOn,
+    /// BigQuery's `* except (...)`, given the wildcard followed by the columns it leaves out.
+    Except,
+    /// Snowflake's `lateral` ahead of a table function, e.g. `lateral flatten(...)`, given the
+    /// call of the table function.
+    Lateral,
+    /// Snowflake's `qualify`, given the condition of the `having` clause, if any, followed by
+    /// that of the `qualify` clause. It is marked as the condition of the `having` clause.
+    Qualify,
+    /// BigQuery's `* replace (...)`, given the wildcard followed by each replacement expression
+    /// and the name of the column it replaces.
+    Replace,
+    /// BigQuery's `safe_cast(...)`, given the cast.
+    SafeCast,
+    /// BigQuery's `with offset` following an `unnest`, given the alias of the offset, if any. It
+    /// is marked as the table hint of the `unnest`.
+    WithOffset,
+}
+
+impl Syntax {
+    const ALL: [Syntax; 9] = [
+        Syntax::Array,
+        Syntax::ArraySubquery,
+        Syntax::DistinctOn,
+        Syntax::Except,
+        Syntax::Lateral,
+        Syntax::Qualify,
+        Syntax::Replace,
+        Syntax::SafeCast,
+        Syntax::WithOffset,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Syntax::Array => "array",
+            Syntax::ArraySubquery => "array_subquery",
+            Syntax::DistinctOn => "distinct_on",
+            Syntax::Except => "except",
+            Syntax::Lateral => "lateral",
+            Syntax::Qualify => "qualify",
+            Syntax::Replace => "replace",
+            Syntax::SafeCast => "safe_cast",
+            Syntax::WithOffset => "with_offset",
+        }
+    }
+}
+
+/// The choices recovered from the source SQL.
+#[derive(Debug)]
+pub struct Scan {
+    /// The prefix marking the aliases introduced with `as`.
+    alias_mark: String,
+    /// The prefix marking the casts written with `::`.
+    cast_mark: String,
+    /// The data type of every cast written with `::`, indexed by the number within its mark.
+    cast_types: Vec<DataType>,
+    /// The prefix marking the offsets of `limit`s written as `limit offset, count`.
+    limit_mark: String,
+    /// The offset of every `limit offset, count`, indexed by the number within its mark.
+    limit_offsets: Vec<Expr>,
+    /// The prefix marking the operators the parser does not understand.
+    operator_mark: String,
+    /// Every operator the parser does not understand, indexed by the number within its mark.
+    operators: Vec<&'static str>,
+    /// The prefix of the names of the functions standing in for syntax the parser does not
+    /// understand.
+    syntax_mark: String,
+    /// The prefix marking the data types the parser does not understand.
+    type_mark: String,
+    /// Every data type the parser does not understand as written, indexed by the number within
+    /// its mark.
+    types: Vec<String>,
+}
+
+impl Scan {
+    /// Scans the given tokens, returning them with the choices the parser would lose track of
+    /// marked within them.
+    ///
+    /// MySQL's `limit offset, count` is only accepted, and rewritten, in the `mysql` dialect, as is
+    /// the syntax of other dialects which the parser does not understand in those dialects.
+    pub fn new(tokens: &[Token], dialect: Dialect) -> (Self, Vec<Token>) {
+        let alias_mark = unique_mark("forma_as_", tokens);
+        let cast_mark = unique_mark("forma_cast_", tokens);
+        let limit_mark = unique_mark("forma_limit_", tokens);
+        let operator_mark = unique_mark("forma_op_", tokens);
+        let syntax_mark = unique_mark("forma_syntax_", tokens);
+        let type_mark = unique_mark("forma_type_", tokens);
+        // Aliases are marked first, so that those which `mark_syntax` moves keep their mark.
+        let marked = mark_explicit_aliases(tokens, &alias_mark);
+        let (marked, types) = mark_syntax(&marked, &syntax_mark, &type_mark, dialect);
+        let (marked, operators) = mark_operators(&marked, &operator_mark, dialect);
+        let (marked, cast_types) = mark_double_colon_casts(&marked, &cast_mark);
+        let (marked, limit_offsets) = match dialect {
+            Dialect::MySql => mark_comma_limits(&marked, &limit_mark),
+            _ => (marked, vec![]),
+        };
+        let scan = Scan {
+            alias_mark,
+            cast_mark,
+            cast_types,
+            limit_mark,
+            limit_offsets,
+            operator_mark,
+            operators,
+            syntax_mark,
+            type_mark,
+            types,
+        };
+        (scan, marked)
+    }
+
+    /// Removes the mark from an alias, returning the alias as written along with whether it was
+    /// introduced with `as`.
+    pub fn unmark_alias(&self, alias: Ident) -> (Ident, bool) {
+        match alias.value.strip_prefix(&self.alias_mark) {
+            Some(value) => (
+                Ident {
+                    value: value.to_owned(),
+                    quote_style: alias.quote_style,
+                },
+                true,
+            ),
+            None => (alias, false),
+        }
+    }
+
+    /// Removes the mark from the data type of a cast, returning the data type as written along
+    /// with how the cast was written.
+    pub fn unmark_cast(&self, data_type: DataType) -> (DataType, CastStyle) {
+        if let DataType::Custom(name) = &data_type {
+            if let Some(text) = self.unmark_type(name) {
+                let data_type = DataType::Custom(ObjectName(vec![Ident::new(text)]));
+                return (data_type, CastStyle::Function);
+            }
+        }
+        let cast_type = match &data_type {
+            DataType::Custom(ObjectName(idents)) => match idents.as_slice() {
+                [Ident {
+                    value,
+                    quote_style: None,
+                }] => value
+                    .strip_prefix(&self.cast_mark)
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| self.cast_types.get(index)),
+                _ => None,
+            },
+            _ => None,
+        };
+        match cast_type {
+            Some(cast_type) => (cast_type.clone(), CastStyle::DoubleColon),
+            None => (data_type, CastStyle::Function),
+        }
+    }
+
+    /// Removes the mark from the offset of a `limit`, returning the offset as written along with
+    /// how the `limit` was written.
+    pub fn unmark_offset(&self, offset: Expr) -> (Expr, LimitStyle) {
+        let limit_offset = match &offset {
+            Expr::Value(Value::Number(value)) => value
+                .strip_prefix(&self.limit_mark)
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.limit_offsets.get(index)),
+            _ => None,
+        };
+        match limit_offset {
+            Some(limit_offset) => (limit_offset.clone(), LimitStyle::Comma),
+            None => (offset, LimitStyle::Standard),
+        }
+    }
+
+    /// Returns the operator which the given operand of a `|` operator marks, if any.
+    pub fn unmark_operator(&self, operand: &Expr) -> Option<&'static str> {
+        match operand {
+            Expr::Identifier(Ident {
+                value,
+                quote_style: None,
+            }) => value
+                .strip_prefix(&self.operator_mark)
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.operators.get(index))
+                .copied(),
+            _ => None,
+        }
+    }
+
+    /// Returns the syntax which a call of the function of the given name stands in for, if any.
+    pub fn unmark_function(&self, name: &ObjectName) -> Option<Syntax> {
+        match name.0.as_slice() {
+            [Ident {
+                value,
+                quote_style: None,
+            }] => value.strip_prefix(&self.syntax_mark).and_then(|name| {
+                Syntax::ALL
+                    .iter()
+                    .copied()
+                    .find(|syntax| syntax.name() == name)
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the data type as written which the word of the given name stands in for, if any.
+    /// The word is a function name where it constructs a value, e.g. `struct<a int64>(1)`.
+    pub fn unmark_type(&self, name: &ObjectName) -> Option<&str> {
+        match name.0.as_slice() {
+            [Ident {
+                value,
+                quote_style: None,
+            }] => value
+                .strip_prefix(&self.type_mark)
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.types.get(index))
+                .map(String::as_str),
+            _ => None,
+        }
+    }
+}
+
+/// Returns a mark starting with `base` which none of the given tokens contains, so that marked
+/// words cannot be confused with any written in the source SQL.
+fn unique_mark(base: &str, tokens: &[Token]) -> String {
+    let mut mark = base.to_owned();
+    while tokens.iter().any(|token| token.to_string().contains(&mark)) {
+        mark.push('_');
+    }
+    mark
+}
+
+fn is_keyword(token: &Token, expected: Keyword) -> bool {
+    matches!(token, Token::Word(Word { keyword, .. }) if *keyword == expected)
+}
+
+/// Returns `true` if the token is the given unquoted word, which sqlparser need not know as a
+/// keyword.
+fn is_word(token: &Token, expected: &str) -> bool {
+    matches!(
+        token,
+        Token::Word(Word { value, quote_style: None, .. }) if value.eq_ignore_ascii_case(expected)
+    )
+}
+
+fn significant(tokens: &[Token]) -> Vec<&Token> {
+    tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .collect()
+}
+
+/// Marks the aliases which directly follow an `as` keyword, prefixing them with `mark`.
+///
+/// The `as` within a cast, e.g. `cast(a as int)` or BigQuery's `safe_cast(a as int64)`, or a T-SQL
+/// declaration, e.g. `declare @a as int`, gives a data type rather than an alias, so is passed
+/// over.
+fn mark_explicit_aliases(tokens: &[Token], mark: &str) -> Vec<Token> {
+    let positions: Vec<usize> = (0..tokens.len())
+        .filter(|&position| !matches!(tokens[position], Token::Whitespace(_)))
+        .collect();
+    let mut marked = tokens.to_vec();
+    // Whether each enclosing parenthesis belongs to a cast.
+    let mut casts = vec![];
+    for (index, &position) in positions.iter().enumerate() {
+        let previous = |distance: usize| {
+            index
+                .checked_sub(distance)
+                .map(|index| &tokens[positions[index]])
+        };
+        match &tokens[position] {
+            Token::LParen => casts.push(matches!(
+                previous(1),
+                Some(token) if is_keyword(token, Keyword::CAST) || is_word(token, "SAFE_CAST")
+            )),
+            Token::RParen => {
+                casts.pop();
+            }
+            token
+                if is_keyword(token, Keyword::AS)
+                    && casts.last() != Some(&true)
+                    && !matches!(previous(2), Some(token) if is_keyword(token, Keyword::DECLARE)) =>
+            {
+                if let Some(&alias) = positions.get(index + 1) {
+                    marked[alias] = match &tokens[alias] {
+                        Token::Word(word) => Token::Word(Word {
+                            value: format!("{}{}", mark, word.value),
+                            ..word.clone()
+                        }),
+                        Token::SingleQuotedString(value) => {
+                            Token::SingleQuotedString(format!("{}{}", mark, value))
+                        }
+                        token => token.clone(),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    marked
+}
+
+/// Marks the casts written with `::`, replacing the data type following each with a word made up
+/// of `mark` and the index of the data type among those returned.
+///
+/// The parser takes the word for a data type of its own, so that the cast it parses carries the
+/// mark. A data type which does not parse is left alone, for the parser to report.
+fn mark_double_colon_casts(tokens: &[Token], mark: &str) -> (Vec<Token>, Vec<DataType>) {
+    // No data type runs to more than a handful of tokens, e.g. `numeric(10, 2)`.
+    const MAX_DATA_TYPE_LEN: usize = 16;
+    let positions: Vec<usize> = (0..tokens.len())
+        .filter(|&position| !matches!(tokens[position], Token::Whitespace(_)))
+        .collect();
+    let mut marked = vec![];
+    let mut data_types = vec![];
+    let mut position = 0;
+    for (index, &colons) in positions.iter().enumerate() {
+        if colons < position || tokens[colons] != Token::DoubleColon {
+            continue;
+        }
+        let window: Vec<Token> = positions[index + 1..]
+            .iter()
+            .take(MAX_DATA_TYPE_LEN)
+            .map(|&position| tokens[position].clone())
+            .collect();
+        let mut parser = Parser::new(window.clone());
+        if let Ok(data_type) = parser.parse_data_type() {
+            let mut remaining = 0;
+            while parser.next_token() != Token::EOF {
+                remaining += 1;
+            }
+            let last = positions[index + window.len() - remaining];
+            marked.extend_from_slice(&tokens[position..=colons]);
+            marked.push(Token::make_word(
+                &format!("{}{}", mark, data_types.len()),
+                None,
+            ));
+            data_types.push(data_type);
+            position = last + 1;
+        }
+    }
+    marked.extend_from_slice(&tokens[position..]);
+    (marked, data_types)
+}
+
+/// Marks MySQL's `limit offset, count`, rewriting it as `limit count offset offset`, which the
+/// parser understands. The offset is replaced with a number made up of `mark` and the index of the
+/// offset among those returned, which the parser takes as it would any other.
+///
+/// An offset which is not a number is left alone, for the parser to report.
+fn mark_comma_limits(tokens: &[Token], mark: &str) -> (Vec<Token>, Vec<Expr>) {
+    let positions: Vec<usize> = (0..tokens.len())
+        .filter(|&position| !matches!(tokens[position], Token::Whitespace(_)))
+        .collect();
+    let mut marked = vec![];
+    let mut offsets = vec![];
+    let mut position = 0;
+    for window in positions.windows(4) {
+        let (limit, offset, comma, count) = (window[0], window[1], window[2], window[3]);
+        if limit < position
+            || !is_keyword(&tokens[limit], Keyword::LIMIT)
+            || tokens[comma] != Token::Comma
+        {
+            continue;
+        }
+        if let Token::Number(value) = &tokens[offset] {
+            marked.extend_from_slice(&tokens[position..offset]);
+            marked.extend(vec![
+                tokens[count].clone(),
+                Token::Whitespace(Whitespace::Space),
+                Token::make_keyword("OFFSET"),
+                Token::Whitespace(Whitespace::Space),
+                Token::Number(format!("{}{}", mark, offsets.len())),
+            ]);
+            offsets.push(Expr::Value(Value::Number(value.clone())));
+            position = count + 1;
+        }
+    }
+    marked.extend_from_slice(&tokens[position..]);
+    (marked, offsets)
+}
+
+/// Returns the position of the bracket closing the one at `open`, if it is closed.
+fn closing(tokens: &[Token], open: usize) -> Option<usize> {
+    let close = match tokens[open] {
+        Token::LParen => Token::RParen,
+        Token::LBracket => Token::RBracket,
+        Token::Lt => Token::Gt,
+        _ => return None,
+    };
+    let mut depth = 0;
+    for (position, token) in tokens.iter().enumerate().skip(open) {
+        if *token == tokens[open] {
+            depth += 1;
+        } else if *token == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(position);
+            }
+        }
+    }
+    None
+}
+
+/// Writes a data type the parser does not understand back out, with a space between consecutive
+/// words and following each comma, e.g. `struct<a int64, b array<string>>`.
+fn type_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Token> = None;
+    for token in significant(tokens) {
+        if matches!(
+            (previous, token),
+            (Some(Token::Comma), _)
+                | (
+                    Some(Token::Word(_) | Token::Number(_)),
+                    Token::Word(_) | Token::Number(_)
+                )
+        ) {
+            text.push(' ');
+        }
+        text.push_str(&token.to_string());
+        previous = Some(token);
+    }
+    text
+}
+
+/// Marks the syntax of the given dialect which the parser does not understand, rewriting each as
+/// a call of a function named with `mark` followed by the name of the syntax, e.g.
+/// `array[1, 2]` as `forma_syntax_array(1, 2)`.
+///
+/// BigQuery's data types which the parser does not understand, e.g. `array<int64>`, are each
+/// replaced with a word made up of `type_mark` and the index of the data type among those
+/// returned, which the parser takes for a data type or a function name of its own.
+fn mark_syntax(
+    tokens: &[Token],
+    mark: &str,
+    type_mark: &str,
+    dialect: Dialect,
+) -> (Vec<Token>, Vec<String>) {
+    let positions: Vec<usize> = (0..tokens.len())
+        .filter(|&position| !matches!(tokens[position], Token::Whitespace(_)))
+        .collect();
+    // The tokens standing in for each token of the source SQL.
+    let mut marked: Vec<Vec<Token>> = tokens.iter().map(|token| vec![token.clone()]).collect();
+    let mut types = vec![];
+    // The position following the last data type marked, ahead of which nothing more is marked.
+    let mut end = 0;
+    // The position of the `having` of the query at each depth of parentheses, if it has one.
+    let mut havings = vec![None];
+    let call = |syntax: Syntax| Token::make_word(&format!("{}{}", mark, syntax.name()), None);
+    for (index, &position) in positions.iter().enumerate() {
+        if position < end {
+            continue;
+        }
+        let next = |distance: usize| positions.get(index + distance).copied();
+        let is_next = |distance: usize, expected: &Token| {
+            next(distance).is_some_and(|position| tokens[position] == *expected)
+        };
+        let is_next_keyword = |distance: usize, expected: Keyword| {
+            next(distance).is_some_and(|position| is_keyword(&tokens[position], expected))
+        };
+        let postgresql = dialect == Dialect::PostgreSql;
+        let bigquery = dialect == Dialect::BigQuery;
+        let snowflake = dialect == Dialect::Snowflake;
+        let token = &tokens[position];
+        match token {
+            Token::LParen => havings.push(None),
+            Token::RParen if havings.len() > 1 => {
+                havings.pop();
+            }
+            token if is_keyword(token, Keyword::SELECT) => *havings.last_mut().unwrap() = None,
+            token if is_keyword(token, Keyword::HAVING) => {
+                *havings.last_mut().unwrap() = Some(position)
+            }
+            _ => {}
+        }
+        if bigquery
+            && (is_keyword(token, Keyword::ARRAY) || is_word(token, "STRUCT"))
+            && is_next(1, &Token::Lt)
+        {
+            // An array constructed with its type, e.g. `array<int64>[1, 2]`, is called like a
+            // function of the type.
+            if let Some(close) = closing(tokens, positions[index + 1]) {
+                marked[position] = vec![Token::make_word(
+                    &format!("{}{}", type_mark, types.len()),
+                    None,
+                )];
+                for tokens in &mut marked[position + 1..=close] {
+                    tokens.clear();
+                }
+                types.push(type_text(&tokens[position..=close]));
+                end = close + 1;
+                let open = positions.iter().copied().find(|&position| position > close);
+                if let Some(open) = open.filter(|&open| tokens[open] == Token::LBracket) {
+                    if let Some(close) = closing(tokens, open) {
+                        marked[open] = vec![Token::LParen];
+                        marked[close] = vec![Token::RParen];
+                    }
+                }
+            }
+        } else if (postgresql || bigquery)
+            && is_keyword(token, Keyword::ARRAY)
+            && is_next(1, &Token::LBracket)
+        {
+            let open = positions[index + 1];
+            if let Some(close) = closing(tokens, open) {
+                marked[position] = vec![call(Syntax::Array)];
+                marked[open] = vec![Token::LParen];
+                marked[close] = vec![Token::RParen];
+            }
+        } else if (postgresql || bigquery)
+            && is_keyword(token, Keyword::ARRAY)
+            && is_next(1, &Token::LParen)
+            && (is_next_keyword(2, Keyword::SELECT) || is_next_keyword(2, Keyword::WITH))
+        {
+            // The subquery is given as the only argument, within parentheses of its own.
+            let open = positions[index + 1];
+            if let Some(close) = closing(tokens, open) {
+                marked[position] = vec![call(Syntax::ArraySubquery)];
+                marked[open] = vec![Token::LParen, Token::LParen];
+                marked[close] = vec![Token::RParen, Token::RParen];
+            }
+        } else if postgresql
+            && is_keyword(token, Keyword::DISTINCT)
+            && is_next_keyword(1, Keyword::ON)
+            && is_next(2, &Token::LParen)
+        {
+            // The expressions become the first item of the projection, which follows them.
+            if let Some(close) = closing(tokens, positions[index + 2]) {
+                marked[positions[index + 1]] = vec![call(Syntax::DistinctOn)];
+                marked[close].push(Token::Comma);
+            }
+        } else if bigquery && is_word(token, "SAFE_CAST") && is_next(1, &Token::LParen) {
+            // The cast is given as the only argument, so that it is parsed as any other.
+            if let Some(close) = closing(tokens, positions[index + 1]) {
+                marked[position] = vec![
+                    call(Syntax::SafeCast),
+                    Token::LParen,
+                    Token::make_keyword("CAST"),
+                ];
+                marked[close].push(Token::RParen);
+            }
+        } else if bigquery
+            && (is_keyword(token, Keyword::EXCEPT) || is_word(token, "REPLACE"))
+            && index > 0
+            && tokens[positions[index - 1]] == Token::Mult
+            && is_next(1, &Token::LParen)
+            && !(is_next_keyword(2, Keyword::SELECT) || is_next_keyword(2, Keyword::WITH))
+        {
+            // The wildcard, e.g. `t.*`, is given as the first argument, followed by the columns.
+            // Each replacement is followed by the name of the column it replaces in place of `as`.
+            let mut start = index - 1;
+            while start >= 2
+                && tokens[positions[start - 1]] == Token::Period
+                && matches!(tokens[positions[start - 2]], Token::Word(_))
+            {
+                start -= 2;
+            }
+            let open = positions[index + 1];
+            if let Some(close) = closing(tokens, open) {
+                let syntax = if is_word(token, "REPLACE") {
+                    Syntax::Replace
+                } else {
+                    Syntax::Except
+                };
+                marked[positions[start]].splice(0..0, vec![call(syntax), Token::LParen]);
+                marked[position] = vec![Token::Comma];
+                marked[open] = vec![];
+                let mut depth = 0;
+                for position in open..close {
+                    match &tokens[position] {
+                        Token::LParen => depth += 1,
+                        Token::RParen => depth -= 1,
+                        token if depth == 1 && is_keyword(token, Keyword::AS) => {
+                            marked[position] = vec![Token::Comma]
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        } else if bigquery
+            && is_keyword(token, Keyword::WITH)
+            && is_next_keyword(1, Keyword::OFFSET)
+            && !(is_next_keyword(2, Keyword::AS) && is_next(3, &Token::LParen))
+        {
+            // The alias of the offset, if any, is given as the only argument, keeping its mark.
+            let offset = positions[index + 1];
+            let alias = if is_next_keyword(2, Keyword::AS) {
+                marked[positions[index + 2]] = vec![];
+                next(3)
+            } else {
+                next(2).filter(|&alias| match &tokens[alias] {
+                    Token::Word(Word { keyword, .. }) => {
+                        !RESERVED_FOR_TABLE_ALIAS.contains(keyword)
+                    }
+                    _ => false,
+                })
+            };
+            marked[offset] = vec![Token::LParen, call(Syntax::WithOffset), Token::LParen];
+            match alias {
+                Some(alias) => marked[alias].extend(vec![Token::RParen, Token::RParen]),
+                None => marked[offset].extend(vec![Token::RParen, Token::RParen]),
+            }
+        } else if snowflake
+            && is_keyword(token, Keyword::LATERAL)
+            && next(1).is_some_and(|position| matches!(tokens[position], Token::Word(_)))
+            && is_next(2, &Token::LParen)
+        {
+            // The call of the table function is given as the only argument.
+            if let Some(close) = closing(tokens, positions[index + 2]) {
+                marked[position] = vec![call(Syntax::Lateral), Token::LParen];
+                marked[close].push(Token::RParen);
+            }
+        } else if snowflake && is_word(token, "QUALIFY") {
+            // The condition runs for as long as the parser takes it to, and is given as the last
+            // argument within the condition of a `having` clause, which is added where the query
+            // has none. A condition which does not parse is left alone, for the parser to report.
+            let window: Vec<Token> = positions[index + 1..]
+                .iter()
+                .map(|&position| tokens[position].clone())
+                .collect();
+            let mut parser = Parser::new(window.clone());
+            if parser.parse_expr().is_ok() {
+                let mut remaining = 0;
+                while parser.next_token() != Token::EOF {
+                    remaining += 1;
+                }
+                let last = positions[index + window.len() - remaining];
+                match havings.last().copied().flatten() {
+                    Some(having) => {
+                        marked[having].extend(vec![
+                            Token::Whitespace(Whitespace::Space),
+                            call(Syntax::Qualify),
+                            Token::LParen,
+                        ]);
+                        marked[position] = vec![Token::Comma];
+                    }
+                    None => {
+                        marked[position] = vec![
+                            Token::make_keyword("HAVING"),
+                            Token::Whitespace(Whitespace::Space),
+                            call(Syntax::Qualify),
+                            Token::LParen,
+                        ]
+                    }
+                }
+                marked[last].push(Token::RParen);
+            }
+        }
+    }
+    (marked.into_iter().flatten().collect(), types)
+}
+
+/// Returns the operators of the given dialect which the parser does not understand, longest first
+/// so that e.g. `->>` is not taken for `->`.
+fn dialect_operators(dialect: Dialect) -> &'static [&'static str] {
+    match dialect {
+        Dialect::PostgreSql => &[
+            "->>",
+            "->",
+            "#>>",
+            "#>",
+            "#-",
+            "@>",
+            "<@",
+            "@@",
+            "?|",
+            "?&",
+            "?",
+            "not ilike",
+            "ilike",
+        ],
+        Dialect::Snowflake => &["=>", "not ilike", "ilike"],
+        _ => &[],
+    }
+}
+
+/// Returns the number of tokens making up the given operator at the start of the tokens, if it is
+/// there. The words of an operator may be separated by whitespace, e.g. `not ilike`, where the
+/// tokens of a symbol must be adjacent, e.g. `->>`.
+fn operator_len(tokens: &[Token], operator: &str) -> Option<usize> {
+    if operator.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+        let mut len = 0;
+        for (index, word) in operator.split(' ').enumerate() {
+            if index > 0 {
+                while matches!(tokens.get(len), Some(Token::Whitespace(_))) {
+                    len += 1;
+                }
+            }
+            match tokens.get(len) {
+                Some(Token::Word(Word {
+                    value,
+                    quote_style: None,
+                    ..
+                })) if value.eq_ignore_ascii_case(word) => len += 1,
+                _ => return None,
+            }
+        }
+        Some(len)
+    } else {
+        let mut symbol = String::new();
+        for (len, token) in tokens.iter().enumerate() {
+            symbol.push_str(&token.to_string());
+            if symbol == operator {
+                return Some(len + 1);
+            } else if !operator.starts_with(&symbol) {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// Marks the operators of the given dialect which the parser does not understand, e.g.
+/// PostgreSQL's `->>`, rewriting each as a `|` operator on either side of a word made up of `mark`
+/// and the index of the operator among those returned. `a ->> 'b'` becomes `a | m_0 | 'b'`, which
+/// the parser takes as `(a | m_0) | 'b'`.
+///
+/// Like the operators PostgreSQL does not name, `|` binds looser than arithmetic and tighter than
+/// comparisons.
+fn mark_operators(
+    tokens: &[Token],
+    mark: &str,
+    dialect: Dialect,
+) -> (Vec<Token>, Vec<&'static str>) {
+    let mut marked = vec![];
+    let mut operators = vec![];
+    let mut position = 0;
+    'tokens: while position < tokens.len() {
+        for &operator in dialect_operators(dialect) {
+            if let Some(len) = operator_len(&tokens[position..], operator) {
+                marked.extend(vec![
+                    Token::Pipe,
+                    Token::make_word(&format!("{}{}", mark, operators.len()), None),
+                    Token::Pipe,
+                ]);
+                operators.push(operator);
+                position += len;
+                continue 'tokens;
+            }
+        }
+        marked.push(tokens[position].clone());
+        position += 1;
+    }
+    (marked, operators)
+}
+
+/// Rejects PostgreSQL's `distinct on (...)` outside of the `postgresql` dialect, which marks it
+/// when scanning. sqlparser would otherwise take it for a call of a function named `on`, changing
+/// the meaning of the query.
+pub fn reject_distinct_on(tokens: &[Token]) -> Result<(), ParserError> {
+    let tokens = significant(tokens);
+    let distinct_on = tokens.windows(3).any(|window| {
+        is_keyword(window[0], Keyword::DISTINCT)
+            && is_keyword(window[1], Keyword::ON)
+            && window[2] == &Token::LParen
+    });
+    if distinct_on {
+        Err(ParserError::ParserError(
+            "DISTINCT ON is not supported".to_owned(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes the given tokens back out as SQL.
+#[cfg(test)]
+pub fn to_sql(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            // The tokenizer unescapes quotes within strings, so they must be escaped once more.
+            Token::SingleQuotedString(value) => format!("'{}'", value.replace('\'', "''")),
+            Token::NationalStringLiteral(value) => format!("N'{}'", value.replace('\'', "''")),
+            token => token.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use sqlparser::tokenizer::Tokenizer;
+
+    use crate::dialect::TemplatedDialect;
+
+    #[test]
+    fn test_mark_explicit_aliases() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(
+            &dialect,
+            "select a as x, b y, c as 'z', cast(d as int) from t1 t, t2 as t",
+        )
+        .tokenize()
+        .unwrap();
+        assert_eq!(
+            to_sql(&mark_explicit_aliases(&tokens, "m_")),
+            "select a as m_x, b y, c as 'm_z', cast(d as int) from t1 t, t2 as m_t"
+        );
+    }
+
+    #[test]
+    fn test_unmark_alias() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(&dialect, "select forma_as_a as b")
+            .tokenize()
+            .unwrap();
+        let (scan, _) = Scan::new(&tokens, Dialect::Templated);
+        let ident = |value: &str| Ident {
+            value: value.to_owned(),
+            quote_style: None,
+        };
+        // The mark cannot be mistaken for a prefix written in the source SQL.
+        assert_eq!(
+            scan.unmark_alias(ident("forma_as_a")),
+            (ident("forma_as_a"), false)
+        );
+        assert_eq!(scan.unmark_alias(ident("forma_as__b")), (ident("b"), true));
+    }
+
+    #[test]
+    fn test_mark_double_colon_casts() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(
+            &dialect,
+            "select cast(a::int as text), b :: timestamp with time zone, c::numeric(10, 2) from t1",
+        )
+        .tokenize()
+        .unwrap();
+        let (marked, data_types) = mark_double_colon_casts(&tokens, "m_");
+        assert_eq!(
+            to_sql(&marked),
+            "select cast(a::m_0 as text), b ::m_1, c::m_2 from t1"
+        );
+        assert_eq!(
+            data_types,
+            vec![
+                DataType::Int,
+                DataType::Timestamp,
+                DataType::Decimal(Some(10), Some(2))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unmark_cast() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(&dialect, "select cast(a::int as text)")
+            .tokenize()
+            .unwrap();
+        let (scan, _) = Scan::new(&tokens, Dialect::Templated);
+        let custom = |value: &str| {
+            DataType::Custom(ObjectName(vec![Ident {
+                value: value.to_owned(),
+                quote_style: None,
+            }]))
+        };
+        assert_eq!(
+            scan.unmark_cast(custom("forma_cast_0")),
+            (DataType::Int, CastStyle::DoubleColon)
+        );
+        assert_eq!(
+            scan.unmark_cast(custom("forma_cast_1")),
+            (custom("forma_cast_1"), CastStyle::Function)
+        );
+        assert_eq!(
+            scan.unmark_cast(DataType::Text),
+            (DataType::Text, CastStyle::Function)
+        );
+    }
+
+    #[test]
+    fn test_mark_comma_limits() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(
+            &dialect,
+            "select a, 'it''s' from t1 limit 10 , 20;\nSELECT 1 LIMIT 5, 2 limit 3",
+        )
+        .tokenize()
+        .unwrap();
+        let (marked, offsets) = mark_comma_limits(&tokens, "m_");
+        assert_eq!(
+            to_sql(&marked),
+            "select a, 'it''s' from t1 limit 20 OFFSET m_0;\nSELECT 1 LIMIT 2 OFFSET m_1 limit 3"
+        );
+        let number = |n: &str| Expr::Value(Value::Number(n.to_owned()));
+        assert_eq!(offsets, vec![number("10"), number("5")]);
+    }
+
+    #[test]
+    fn test_unmark_offset() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(&dialect, "select 1 limit 10, 20")
+            .tokenize()
+            .unwrap();
+        let (scan, _) = Scan::new(&tokens, Dialect::MySql);
+        let number = |n: &str| Expr::Value(Value::Number(n.to_owned()));
+        assert_eq!(
+            scan.unmark_offset(number("forma_limit_0")),
+            (number("10"), LimitStyle::Comma)
+        );
+        assert_eq!(
+            scan.unmark_offset(number("5")),
+            (number("5"), LimitStyle::Standard)
+        );
+    }
+
+    #[test]
+    fn test_mark_syntax() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(
+            &dialect,
+            "select distinct on (a) array[array[1], (2)], array(select 1) from t1",
+        )
+        .tokenize()
+        .unwrap();
+        assert_eq!(
+            to_sql(&mark_syntax(&tokens, "m_", "t_", Dialect::PostgreSql).0),
+            concat!(
+                "select distinct m_distinct_on (a), m_array(m_array(1), (2)), ",
+                "m_array_subquery((select 1)) from t1"
+            )
+        );
+        assert_eq!(
+            to_sql(&mark_syntax(&tokens, "m_", "t_", Dialect::Generic).0),
+            to_sql(&tokens)
+        );
+        let tokens = Tokenizer::new(
+            &dialect,
+            concat!(
+                "select t.* except (a), * replace (b + 1 as b), safe_cast(c as int64), ",
+                "array<struct<x int64, y string>>[(1, 'a')] from t, unnest(d) with offset as o"
+            ),
+        )
+        .tokenize()
+        .unwrap();
+        let (marked, types) = mark_syntax(&tokens, "m_", "t_", Dialect::BigQuery);
+        assert_eq!(
+            to_sql(&marked),
+            concat!(
+                "select m_except(t.* , a), m_replace(* , b + 1 , b), ",
+                "m_safe_cast(CAST(c as int64)), t_0((1, 'a')) from t, unnest(d) with ",
+                "(m_with_offset(  o))"
+            )
+        );
+        assert_eq!(types, vec!["array<struct<x int64, y string>>"]);
+        let tokens = Tokenizer::new(
+            &dialect,
+            concat!(
+                "select a from t1, lateral flatten(b) group by a having c > 1 qualify d = 1 ",
+                "union select a from (select a from t2 qualify e) order by a"
+            ),
+        )
+        .tokenize()
+        .unwrap();
+        assert_eq!(
+            to_sql(&mark_syntax(&tokens, "m_", "t_", Dialect::Snowflake).0),
+            concat!(
+                "select a from t1, m_lateral( flatten(b)) group by a ",
+                "having m_qualify( c > 1 , d = 1) union select a from (select a from t2 ",
+                "HAVING m_qualify( e)) order by a"
+            )
+        );
+    }
+
+    #[test]
+    fn test_mark_operators() {
+        let dialect = TemplatedDialect {};
+        let tokens = Tokenizer::new(&dialect, "select a->>'b', a - > b, a not  ilike b, a ?| b")
+            .tokenize()
+            .unwrap();
+        let (marked, operators) = mark_operators(&tokens, "m_", Dialect::PostgreSql);
+        assert_eq!(
+            to_sql(&marked),
+            "select a|m_0|'b', a - > b, a |m_1| b, a |m_2| b"
+        );
+        assert_eq!(operators, vec!["->>", "not ilike", "?|"]);
+    }
+}
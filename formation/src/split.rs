@@ -0,0 +1,492 @@
+//! Script splitting
+//!
+//! Some dialects divide a script into parts using lines which are not SQL themselves, such as
+//! T-SQL's `GO` or MySQL's `DELIMITER`. This module separates those parts so that each may be
+//! parsed on its own, while keeping the separating lines to be written back verbatim.
+
+use std::ops::Range;
+
+/// A part of a script, along with the line that ended it, if any.
+#[derive(Debug, PartialEq)]
+pub struct Batch<'a> {
+    /// The SQL of the batch.
+    pub sql: &'a str,
+    /// The delimiter terminating each statement of the batch.
+    pub delimiter: &'a str,
+    /// The separator which ended the batch, without surrounding whitespace.
+    pub separator: Option<&'a str>,
+}
+
+/// How a dialect quotes strings and identifiers. A script is never split within quotes or
+/// comments, e.g. on a `GO` line within a string.
+struct Quoting {
+    /// The characters opening and closing quoted strings and identifiers.
+    quotes: &'static [(u8, u8)],
+    /// Whether a backslash escapes the character following it within quotes, as in MySQL.
+    backslash_escapes: bool,
+    /// Whether `#` starts a line comment, as in MySQL.
+    hash_comments: bool,
+}
+
+const MSSQL: Quoting = Quoting {
+    quotes: &[(b'\'', b'\''), (b'"', b'"'), (b'[', b']')],
+    backslash_escapes: false,
+    hash_comments: false,
+};
+
+const MYSQL: Quoting = Quoting {
+    quotes: &[(b'\'', b'\''), (b'"', b'"'), (b'`', b'`')],
+    backslash_escapes: true,
+    hash_comments: true,
+};
+
+const POSTGRESQL: Quoting = Quoting {
+    quotes: &[(b'\'', b'\''), (b'"', b'"')],
+    backslash_escapes: false,
+    hash_comments: false,
+};
+
+impl Quoting {
+    /// Returns the end of the quoted string or identifier, or the comment, which starts at
+    /// `position`, if any. A line comment ends ahead of its newline, and anything left open runs
+    /// to the end of the SQL.
+    fn skip(&self, sql: &str, position: usize) -> Option<usize> {
+        let bytes = sql.as_bytes();
+        let find = |pattern: &str, from: usize| sql[from..].find(pattern).map(|end| from + end);
+        match bytes[position] {
+            b'-' if bytes.get(position + 1) == Some(&b'-') => {
+                Some(find("\n", position).unwrap_or(bytes.len()))
+            }
+            b'#' if self.hash_comments => Some(find("\n", position).unwrap_or(bytes.len())),
+            b'/' if bytes.get(position + 1) == Some(&b'*') => {
+                Some(find("*/", position + 2).map_or(bytes.len(), |end| end + 2))
+            }
+            open => {
+                let &(_, close) = self.quotes.iter().find(|&&(quote, _)| quote == open)?;
+                let mut end = position + 1;
+                while end < bytes.len() {
+                    match bytes[end] {
+                        b'\\' if self.backslash_escapes => end += 2,
+                        byte if byte == close => return Some(end + 1),
+                        _ => end += 1,
+                    }
+                }
+                Some(bytes.len())
+            }
+        }
+    }
+
+    /// Returns the lines of the given SQL which start outside of quotes and comments, along with
+    /// their offsets.
+    fn lines<'a>(&self, sql: &'a str) -> Vec<(usize, &'a str)> {
+        let mut lines = vec![];
+        let mut line_start = Some(0);
+        let mut position = 0;
+        while position < sql.len() {
+            if let Some(start) = line_start.take() {
+                let end = sql[start..].find('\n').map_or(sql.len(), |end| start + end);
+                lines.push((start, &sql[start..end]));
+            }
+            position = match self.skip(sql, position) {
+                Some(end) => end,
+                None => {
+                    if sql.as_bytes()[position] == b'\n' {
+                        line_start = Some(position + 1);
+                    }
+                    position + 1
+                }
+            };
+        }
+        lines
+    }
+}
+
+/// Splits a T-SQL script on its `GO` lines, which may give a repeat count, e.g. `GO 5`.
+pub fn go_batches(sql: &str) -> Vec<Batch<'_>> {
+    let mut batches = vec![];
+    let mut start = 0;
+    for (offset, line) in MSSQL.lines(sql) {
+        if is_go(line) {
+            batches.push(Batch {
+                sql: &sql[start..offset],
+                delimiter: ";",
+                separator: Some(line.trim()),
+            });
+            start = (offset + line.len() + 1).min(sql.len());
+        }
+    }
+    batches.push(Batch {
+        sql: &sql[start..],
+        delimiter: ";",
+        separator: None,
+    });
+    batches
+}
+
+/// Splits a MySQL script on its `DELIMITER` lines, each of which sets the delimiter of the batch
+/// which follows it.
+pub fn delimiter_batches(sql: &str) -> Vec<Batch<'_>> {
+    let mut batches = vec![];
+    let mut delimiter = ";";
+    let mut start = 0;
+    for (offset, line) in MYSQL.lines(sql) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if let [keyword, next] = words.as_slice() {
+            if keyword.eq_ignore_ascii_case("delimiter") {
+                batches.push(Batch {
+                    sql: &sql[start..offset],
+                    delimiter,
+                    separator: Some(line.trim()),
+                });
+                delimiter = next;
+                start = (offset + line.len() + 1).min(sql.len());
+            }
+        }
+    }
+    batches.push(Batch {
+        sql: &sql[start..],
+        delimiter,
+        separator: None,
+    });
+    batches
+}
+
+/// Splits a MySQL batch into its statements on the given delimiter, e.g. `$$`, wherever it appears
+/// outside of quotes and comments.
+pub fn delimited_statements<'a>(sql: &'a str, delimiter: &str) -> Vec<&'a str> {
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut position = 0;
+    while position < sql.len() {
+        position = match MYSQL.skip(sql, position) {
+            Some(end) => end,
+            None if sql.as_bytes()[position..].starts_with(delimiter.as_bytes()) => {
+                statements.push(&sql[start..position]);
+                start = position + delimiter.len();
+                start
+            }
+            None => position + 1,
+        };
+    }
+    statements.push(&sql[start..]);
+    statements
+}
+
+/// Returns the spans of the strings within the given MySQL SQL which are quoted with `"`, e.g.
+/// `"it's"`, outside of other quotes and comments. A doubled quote, e.g. `"a""b"`, continues the
+/// string it is within.
+pub fn double_quoted_strings(sql: &str) -> Vec<Range<usize>> {
+    let mut strings: Vec<Range<usize>> = vec![];
+    let mut position = 0;
+    while position < sql.len() {
+        position = match MYSQL.skip(sql, position) {
+            Some(end) if sql.as_bytes()[position] == b'"' => {
+                match strings.last_mut() {
+                    Some(string) if string.end == position => string.end = end,
+                    _ => strings.push(position..end),
+                }
+                end
+            }
+            Some(end) => end,
+            None => position + 1,
+        };
+    }
+    strings
+}
+
+/// Returns `true` if the given MySQL statement defines a stored program, e.g.
+/// `create definer = admin procedure`, whose body the parser does not understand.
+pub fn is_stored_program(statement: &str) -> bool {
+    let words: Vec<String> = statement
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(&['-', '#'][..]))
+        .flat_map(str::split_whitespace)
+        .map(str::to_lowercase)
+        .collect();
+    let kinds = ["procedure", "function", "trigger", "event"];
+    match words.as_slice() {
+        [create, definer, rest @ ..] if create == "create" && definer.starts_with("definer") => {
+            // The definer may be written with or without spaces around its `=`.
+            rest.iter()
+                .take(3)
+                .any(|word| kinds.contains(&word.as_str()))
+        }
+        [create, kind, ..] => create == "create" && kinds.contains(&kind.as_str()),
+        _ => false,
+    }
+}
+
+fn is_go(line: &str) -> bool {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [go] => go.eq_ignore_ascii_case("go"),
+        [go, count] => go.eq_ignore_ascii_case("go") && count.chars().all(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// A part of a PostgreSQL script.
+#[derive(Debug, PartialEq)]
+pub enum Part<'a> {
+    /// Any number of ordinary statements.
+    Sql(&'a str),
+    /// A statement with a dollar-quoted body, e.g. `create function`.
+    Routine(Routine<'a>),
+}
+
+/// A statement with a dollar-quoted body, split around the body.
+#[derive(Debug, PartialEq)]
+pub struct Routine<'a> {
+    /// Everything ahead of the body's opening quote, e.g. `create function f() returns int as`.
+    pub header: &'a str,
+    /// The dollar quote, e.g. `$$` or `$body$`.
+    pub tag: &'a str,
+    /// The body within the quotes.
+    pub body: &'a str,
+    /// Everything after the body's closing quote up to the terminating `;`, e.g. `language sql`.
+    pub footer: &'a str,
+}
+
+impl Routine<'_> {
+    /// Returns the language the body is written in, if given, e.g. `sql` or `plpgsql`.
+    pub fn language(&self) -> Option<String> {
+        let words: Vec<&str> = self
+            .header
+            .split_whitespace()
+            .chain(self.footer.split_whitespace())
+            .collect();
+        words
+            .windows(2)
+            .find(|pair| pair[0].eq_ignore_ascii_case("language"))
+            .map(|pair| pair[1].trim_matches('\'').to_lowercase())
+    }
+}
+
+/// Separates statements with dollar-quoted bodies from those around them.
+pub fn routine_parts(sql: &str) -> Vec<Part<'_>> {
+    let bytes = sql.as_bytes();
+    let mut parts = vec![];
+    let mut emitted = 0;
+    let mut statement_start = 0;
+    // The start of the opening quote, the start of the body and the end of the body.
+    let mut body = None;
+    let mut position = 0;
+    let routine =
+        |statement_start: usize, (open, start, end): (usize, usize, usize), stop| Routine {
+            header: &sql[statement_start..open],
+            tag: &sql[open..start],
+            body: &sql[start..end],
+            footer: &sql[end + (start - open)..stop],
+        };
+    while position < bytes.len() {
+        if let Some(end) = POSTGRESQL.skip(sql, position) {
+            position = end;
+            continue;
+        }
+        position = match bytes[position] {
+            b'$' if body.is_none() => match dollar_quote(&sql[position..]) {
+                Some(tag) => match sql[position + tag.len()..].find(tag) {
+                    Some(length) => {
+                        let start = position + tag.len();
+                        // Any other dollar-quoted string is a literal.
+                        if is_routine(&sql[statement_start..position]) {
+                            body = Some((position, start, start + length));
+                        }
+                        start + length + tag.len()
+                    }
+                    None => bytes.len(),
+                },
+                None => position + 1,
+            },
+            b';' => {
+                if let Some(body) = body.take() {
+                    parts.push(Part::Sql(&sql[emitted..statement_start]));
+                    parts.push(Part::Routine(routine(statement_start, body, position)));
+                    emitted = position + 1;
+                }
+                statement_start = position + 1;
+                position + 1
+            }
+            _ => position + 1,
+        };
+    }
+    if let Some(body) = body {
+        parts.push(Part::Sql(&sql[emitted..statement_start]));
+        parts.push(Part::Routine(routine(statement_start, body, sql.len())));
+        emitted = sql.len();
+    }
+    parts.push(Part::Sql(&sql[emitted..]));
+    parts
+}
+
+/// Returns `true` if the given start of a statement defines a routine, e.g.
+/// `create or replace function`.
+fn is_routine(header: &str) -> bool {
+    let words: Vec<String> = header
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .flat_map(str::split_whitespace)
+        .map(str::to_lowercase)
+        .collect();
+    match words.as_slice() {
+        [create, or, replace, kind, ..] if or == "or" && replace == "replace" => {
+            create == "create" && (kind == "function" || kind == "procedure")
+        }
+        [create, kind, ..] => create == "create" && (kind == "function" || kind == "procedure"),
+        _ => false,
+    }
+}
+
+/// Returns the dollar quote which starts the given SQL, if any, e.g. `$$` or `$body$`.
+fn dollar_quote(sql: &str) -> Option<&str> {
+    let length = sql[1..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))? + 1;
+    if sql[length..].starts_with('$') && !sql[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        Some(&sql[..=length])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_go_batches() {
+        assert_eq!(
+            go_batches("select 1\nGO\nselect 2;\n  go 3  \nselect gone\n"),
+            vec![
+                Batch {
+                    sql: "select 1\n",
+                    delimiter: ";",
+                    separator: Some("GO")
+                },
+                Batch {
+                    sql: "select 2;\n",
+                    delimiter: ";",
+                    separator: Some("go 3")
+                },
+                Batch {
+                    sql: "select gone\n",
+                    delimiter: ";",
+                    separator: None
+                },
+            ]
+        );
+        // `GO` within a string or a comment does not end a batch.
+        assert_eq!(
+            go_batches("select 'a\nGO\nb' /* c\ngo\n*/ -- d\nGO\n"),
+            vec![
+                Batch {
+                    sql: "select 'a\nGO\nb' /* c\ngo\n*/ -- d\n",
+                    delimiter: ";",
+                    separator: Some("GO")
+                },
+                Batch {
+                    sql: "",
+                    delimiter: ";",
+                    separator: None
+                },
+            ]
+        );
+        assert_eq!(
+            go_batches("select 1\nGO"),
+            vec![
+                Batch {
+                    sql: "select 1\n",
+                    delimiter: ";",
+                    separator: Some("GO")
+                },
+                Batch {
+                    sql: "",
+                    delimiter: ";",
+                    separator: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delimiter_batches() {
+        assert_eq!(
+            delimiter_batches("select 1;\nDELIMITER $$\nselect 2$$\ndelimiter ;\nselect 3;\n"),
+            vec![
+                Batch {
+                    sql: "select 1;\n",
+                    delimiter: ";",
+                    separator: Some("DELIMITER $$")
+                },
+                Batch {
+                    sql: "select 2$$\n",
+                    delimiter: "$$",
+                    separator: Some("delimiter ;")
+                },
+                Batch {
+                    sql: "select 3;\n",
+                    delimiter: ";",
+                    separator: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delimited_statements() {
+        assert_eq!(
+            delimited_statements("select '$$', `a$$` # $$\n$$ select \"\\\"$$\" $$", "$$"),
+            vec!["select '$$', `a$$` # $$\n", " select \"\\\"$$\" ", ""]
+        );
+    }
+
+    #[test]
+    fn test_double_quoted_strings() {
+        let sql = "select \"it's\", 'a\"b', `c\"d`, \"e\"\"f\", \"g\\\"h\" # \"i\"\n";
+        let strings: Vec<&str> = double_quoted_strings(sql)
+            .into_iter()
+            .map(|string| &sql[string])
+            .collect();
+        assert_eq!(strings, vec!["\"it's\"", "\"e\"\"f\"", "\"g\\\"h\""]);
+    }
+
+    #[test]
+    fn test_is_stored_program() {
+        assert!(is_stored_program(
+            "\n# p\nCREATE PROCEDURE p() BEGIN SELECT 1; END"
+        ));
+        assert!(is_stored_program(
+            "create definer = `a`@`%` function f() returns int return 1"
+        ));
+        assert!(is_stored_program(
+            "create definer=admin trigger t before insert on t1"
+        ));
+        assert!(!is_stored_program("create table procedure (a int)"));
+        assert!(!is_stored_program("select 1"));
+    }
+
+    #[test]
+    fn test_routine_parts() {
+        let sql = concat!(
+            "select ';$$', $$;$$;\n",
+            "-- one\ncreate function one() returns int as $body$ select 1 $body$ language 'sql';\n",
+            "select $1"
+        );
+        let parts = routine_parts(sql);
+        assert_eq!(
+            parts,
+            vec![
+                Part::Sql("select ';$$', $$;$$;"),
+                Part::Routine(Routine {
+                    header: "\n-- one\ncreate function one() returns int as ",
+                    tag: "$body$",
+                    body: " select 1 ",
+                    footer: " language 'sql'",
+                }),
+                Part::Sql("\nselect $1"),
+            ]
+        );
+        if let Part::Routine(routine) = &parts[1] {
+            assert_eq!(routine.language(), Some("sql".to_owned()));
+        }
+    }
+}
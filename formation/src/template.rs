@@ -0,0 +1,424 @@
+//! Template tags
+//!
+//! Templated SQL, such as a dbt model, is written with Jinja tags: expressions, e.g. `{{ var }}`,
+//! statements, e.g. `{% if x %}`, and comments, e.g. `{# note #}`. Expressions stand in for a
+//! value or identifier and are masked ahead of parsing. Statement and comment tags are structural,
+//! so this module separates them from the SQL around them, to be written back on lines of their
+//! own.
+//!
+//! dbt's calls are understood a little further: `{{ config(...) }}` is kept on a line of its own,
+//! as is a model made up of a single macro call, and the relations given by `{{ ref(...) }}` and
+//! `{{ source(...) }}` are written in a consistent form.
+
+use std::ops::Range;
+
+use crate::options::{TemplateDelimiters, TemplateKind};
+
+/// The words which open Go template actions that control the template rather than print a value,
+/// e.g. `{{if .Full}}` or `{{end}}`.
+const GO_ACTIONS: &[&str] = &["if", "else", "end", "range", "with", "define", "block"];
+
+/// Returns the delimiters, kind and length of the tag which starts the given SQL, if any.
+///
+/// Where several opening delimiters match, e.g. ERB's `<%` and `<%=`, the longest is taken.
+pub fn tag_at<'a>(
+    sql: &str,
+    templates: &'a [TemplateDelimiters],
+) -> Option<(&'a TemplateDelimiters, TemplateKind, usize)> {
+    let delimiters = templates
+        .iter()
+        .filter(|delimiters| !delimiters.open.is_empty() && sql.starts_with(&delimiters.open))
+        .max_by_key(|delimiters| delimiters.open.len())?;
+    let kind = tag_kind(&sql[delimiters.open.len()..], delimiters);
+    let bytes = sql.as_bytes();
+    let mut position = delimiters.open.len();
+    while position < bytes.len() {
+        if sql[position..].starts_with(&delimiters.close) {
+            return Some((delimiters, kind, position + delimiters.close.len()));
+        }
+        position = match bytes[position] {
+            // Strings within the tag may hold its closing delimiter.
+            quote @ b'\'' | quote @ b'"' if kind != TemplateKind::Comment => sql[position + 1..]
+                .find(quote as char)
+                .map_or(bytes.len(), |end| position + end + 2),
+            _ => position + 1,
+        };
+    }
+    None
+}
+
+/// Returns the kind of a tag opened by the given delimiters, given the text following them.
+///
+/// Mustache, Handlebars and Go templates share `{{` and `}}` between expressions, sections and
+/// comments, telling them apart by how the tag starts. Sections, e.g. `{{#each xs}}`,
+/// `{{^empty}}`, `{{/each}}`, `{{else}}` or Go's `{{range .Rows}}` and `{{end}}`, are statements,
+/// while `{{! note }}` and Go's `{{/* note */}}` are comments.
+fn tag_kind(inner: &str, delimiters: &TemplateDelimiters) -> TemplateKind {
+    if delimiters.kind != TemplateKind::Expression || delimiters.open != "{{" {
+        return delimiters.kind;
+    }
+    let inner = inner.trim_start_matches('-').trim_start();
+    let word_end = inner
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(inner.len());
+    let (word, rest) = inner.split_at(word_end);
+    if inner.starts_with('!') || inner.starts_with("/*") {
+        TemplateKind::Comment
+    } else if inner.starts_with(&['#', '^', '/'][..])
+        || (GO_ACTIONS.contains(&word) && rest.starts_with(&[' ', '\t', '\n', '-', '}'][..]))
+    {
+        TemplateKind::Statement
+    } else {
+        TemplateKind::Expression
+    }
+}
+
+/// A part of a templated script.
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    /// Any number of statements, which may hold expression tags.
+    Sql(&'a str),
+    /// A statement or comment tag between statements, along with the indentation of its line.
+    Tag {
+        /// The whitespace ahead of the tag, if it starts its line.
+        indent: &'a str,
+        /// The tag itself.
+        tag: &'a str,
+    },
+    /// A statement interrupted by statement or comment tags, which is kept as written.
+    Verbatim(&'a str),
+}
+
+/// Separates the statement and comment tags of a script from the SQL around them.
+///
+/// Tags between statements become segments of their own. A statement with tags inside it, e.g.
+/// `select a {% if b %}, b{% endif %} from t1`, cannot be parsed and is kept as written.
+pub fn segments<'a>(sql: &'a str, templates: &[TemplateDelimiters]) -> Vec<Segment<'a>> {
+    // Without any tags, there is nothing to separate.
+    if templates
+        .iter()
+        .all(|delimiters| delimiters.open.is_empty() || !sql.contains(&delimiters.open))
+    {
+        return vec![Segment::Sql(sql)];
+    }
+    let bytes = sql.as_bytes();
+    let mut segments = vec![];
+    // The text and tags of the current statement, with `true` marking a tag.
+    let mut pieces = vec![];
+    let mut start = 0;
+    let mut position = 0;
+    while position < bytes.len() {
+        position = match tag_at(&sql[position..], templates) {
+            // A dbt `config(...)` call configures the model as a whole and so stands on its own.
+            Some((delimiters, TemplateKind::Expression, length))
+                if !is_config(&sql[position..position + length], delimiters) =>
+            {
+                position + length
+            }
+            Some((_, _, length)) => {
+                pieces.push((false, start..position));
+                pieces.push((true, position..position + length));
+                start = position + length;
+                start
+            }
+            None => match bytes[position] {
+                quote @ b'\'' | quote @ b'"' => sql[position + 1..]
+                    .find(quote as char)
+                    .map_or(bytes.len(), |end| position + end + 2),
+                b'-' if bytes.get(position + 1) == Some(&b'-') => sql[position..]
+                    .find('\n')
+                    .map_or(bytes.len(), |end| position + end + 1),
+                b'/' if bytes.get(position + 1) == Some(&b'*') => sql[position..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| position + end + 2),
+                b';' => {
+                    pieces.push((false, start..position + 1));
+                    statement_segments(sql, templates, &pieces, &mut segments);
+                    pieces.clear();
+                    start = position + 1;
+                    start
+                }
+                _ => position + 1,
+            },
+        };
+    }
+    pieces.push((false, start..sql.len()));
+    statement_segments(sql, templates, &pieces, &mut segments);
+    segments
+}
+
+fn statement_segments<'a>(
+    sql: &'a str,
+    templates: &[TemplateDelimiters],
+    pieces: &[(bool, Range<usize>)],
+    segments: &mut Vec<Segment<'a>>,
+) {
+    let is_sql = |(is_tag, range): &(bool, Range<usize>)| {
+        !is_tag
+            && !sql[range.clone()]
+                .trim_matches(|c: char| c.is_whitespace() || c == ';')
+                .is_empty()
+    };
+    let tag = |range: &Range<usize>| {
+        let line_start = sql[..range.start].rfind('\n').map_or(0, |end| end + 1);
+        let indent = &sql[line_start..range.start];
+        Segment::Tag {
+            indent: if indent.trim().is_empty() { indent } else { "" },
+            tag: &sql[range.clone()],
+        }
+    };
+    let (first, last) = match (
+        pieces.iter().position(is_sql),
+        pieces.iter().rposition(is_sql),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            segments.extend(
+                pieces
+                    .iter()
+                    .filter(|(is_tag, _)| *is_tag)
+                    .map(|(_, range)| tag(range)),
+            );
+            return;
+        }
+    };
+    segments.extend(
+        pieces[..first]
+            .iter()
+            .filter(|(is_tag, _)| *is_tag)
+            .map(|(_, range)| tag(range)),
+    );
+    let statement = &sql[pieces[first].1.start..pieces[last].1.end];
+    segments.push(if first != last {
+        Segment::Verbatim(statement.trim())
+    } else if is_only_expressions(statement, templates) {
+        // A statement made up of a macro call, e.g. `{{ dbt_utils.union_relations(...) }}`.
+        let start = pieces[first].1.start + statement.len() - statement.trim_start().len();
+        tag(&(start..start + statement.trim().len()))
+    } else {
+        Segment::Sql(statement)
+    });
+    segments.extend(
+        pieces[last + 1..]
+            .iter()
+            .filter(|(is_tag, _)| *is_tag)
+            .map(|(_, range)| tag(range)),
+    );
+}
+
+/// Returns the inner text of the given Jinja expression tag, e.g. `ref('orders')` of
+/// `{{ ref('orders') }}`, unless it controls whitespace, e.g. `{{- x -}}`.
+fn jinja_expression<'a>(tag: &'a str, delimiters: &TemplateDelimiters) -> Option<&'a str> {
+    if delimiters.open != "{{" || delimiters.close != "}}" {
+        return None;
+    }
+    let inner = tag.get(2..tag.len().checked_sub(2)?)?;
+    if inner.starts_with('-') || inner.ends_with('-') {
+        None
+    } else {
+        Some(inner.trim())
+    }
+}
+
+/// Returns the name and arguments of the call making up the given Jinja expression tag, if any.
+fn call<'a>(tag: &'a str, delimiters: &TemplateDelimiters) -> Option<(&'a str, &'a str)> {
+    let expression = jinja_expression(tag, delimiters)?;
+    let open = expression.find('(')?;
+    let name = expression[..open].trim_end();
+    if expression.ends_with(')')
+        && !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    {
+        Some((name, &expression[open + 1..expression.len() - 1]))
+    } else {
+        None
+    }
+}
+
+/// Returns `true` if the given tag is a dbt `config(...)` call.
+fn is_config(tag: &str, delimiters: &TemplateDelimiters) -> bool {
+    matches!(call(tag, delimiters), Some(("config", _)))
+}
+
+/// Returns `true` if the given SQL holds nothing but expression tags.
+fn is_only_expressions(mut sql: &str, templates: &[TemplateDelimiters]) -> bool {
+    loop {
+        sql = sql.trim_start_matches(|c: char| c.is_whitespace() || c == ';');
+        match tag_at(sql, templates) {
+            _ if sql.is_empty() => return true,
+            Some((_, TemplateKind::Expression, length)) => sql = &sql[length..],
+            _ => return false,
+        }
+    }
+}
+
+/// Returns the given dbt `ref(...)` or `source(...)` call, e.g. `{{ref("orders")}}`, written in a
+/// consistent form, e.g. `{{ ref('orders') }}`.
+///
+/// Only calls given nothing but plain strings are rewritten; any other tag is left to be kept as
+/// written.
+pub fn dbt_relation(tag: &str, delimiters: &TemplateDelimiters) -> Option<String> {
+    let (name, arguments) = call(tag, delimiters)?;
+    if name != "ref" && name != "source" {
+        return None;
+    }
+    let arguments = arguments
+        .split(',')
+        .map(|argument| {
+            let argument = argument.trim();
+            let quoted = ["'", "\""].iter().any(|quote| {
+                argument.len() > 1 && argument.starts_with(quote) && argument.ends_with(quote)
+            });
+            let value = argument.get(1..argument.len().saturating_sub(1))?;
+            if !quoted || value.contains(&['\'', '"', '\\'][..]) {
+                None
+            } else {
+                Some(format!("'{}'", value))
+            }
+        })
+        .collect::<Option<Vec<String>>>()?;
+    Some(format!("{{{{ {}({}) }}}}", name, arguments.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn kind_at(sql: &str, templates: &[TemplateDelimiters]) -> Option<(TemplateKind, usize)> {
+        tag_at(sql, templates).map(|(_, kind, length)| (kind, length))
+    }
+
+    #[test]
+    fn test_tag_at() {
+        let jinja = TemplateDelimiters::jinja();
+        assert_eq!(
+            kind_at("{{ var }} x", &jinja),
+            Some((TemplateKind::Expression, 9))
+        );
+        assert_eq!(
+            kind_at("{% set x = '%}' %}", &jinja),
+            Some((TemplateKind::Statement, 18))
+        );
+        assert_eq!(
+            kind_at("{# it's #}", &jinja),
+            Some((TemplateKind::Comment, 10))
+        );
+        // Sections and comments share the delimiters of expressions.
+        assert_eq!(
+            kind_at("{{#each rows}}", &jinja),
+            Some((TemplateKind::Statement, 14))
+        );
+        assert_eq!(
+            kind_at("{{^rows}}", &jinja),
+            Some((TemplateKind::Statement, 9))
+        );
+        assert_eq!(
+            kind_at("{{/each}}", &jinja),
+            Some((TemplateKind::Statement, 9))
+        );
+        assert_eq!(
+            kind_at("{{- range .Rows }}", &jinja),
+            Some((TemplateKind::Statement, 18))
+        );
+        assert_eq!(
+            kind_at("{{end}}", &jinja),
+            Some((TemplateKind::Statement, 7))
+        );
+        assert_eq!(
+            kind_at("{{! it's }}", &jinja),
+            Some((TemplateKind::Comment, 11))
+        );
+        assert_eq!(
+            kind_at("{{/* it's */}}", &jinja),
+            Some((TemplateKind::Comment, 14))
+        );
+        assert_eq!(
+            kind_at("{{ range(3) }}", &jinja),
+            Some((TemplateKind::Expression, 14))
+        );
+        assert_eq!(
+            kind_at("{{ ending }}", &jinja),
+            Some((TemplateKind::Expression, 12))
+        );
+        assert_eq!(kind_at("{{ var", &jinja), None);
+        assert_eq!(kind_at("{ x }", &jinja), None);
+
+        let erb = vec![
+            TemplateDelimiters::new("<%=", "%>", TemplateKind::Expression),
+            TemplateDelimiters::new("<%", "%>", TemplateKind::Statement),
+        ];
+        assert_eq!(
+            kind_at("<%= @since %>", &erb),
+            Some((TemplateKind::Expression, 13))
+        );
+        assert_eq!(
+            kind_at("<% if x %>", &erb),
+            Some((TemplateKind::Statement, 10))
+        );
+    }
+
+    #[test]
+    fn test_dbt_relation() {
+        let jinja = &TemplateDelimiters::jinja()[0];
+        let dbt_relation = |tag| dbt_relation(tag, jinja);
+        assert_eq!(
+            dbt_relation("{{ref( \"orders\" )}}"),
+            Some("{{ ref('orders') }}".to_owned())
+        );
+        assert_eq!(
+            dbt_relation("{{ source('raw',\"events\") }}"),
+            Some("{{ source('raw', 'events') }}".to_owned())
+        );
+        assert_eq!(dbt_relation("{{ ref('orders', v=2) }}"), None);
+        assert_eq!(dbt_relation("{{- ref('orders') }}"), None);
+        assert_eq!(dbt_relation("{{ var('orders') }}"), None);
+    }
+
+    #[test]
+    fn test_segments() {
+        let sql = concat!(
+            "{{ config(materialized='table') }}\n",
+            "{{ dbt_utils.union_relations([ref('a'), ref('b')]) }}\n;\n",
+            "{# orders #}\n",
+            "{% if x %}\n",
+            "select '{%' from {{ t }};\n",
+            "  {% else %}\n",
+            "select a {% if y %}, b{% endif %} from t1;\n",
+            "{% endif %}\n"
+        );
+        assert_eq!(
+            segments(sql, &TemplateDelimiters::jinja()),
+            vec![
+                Segment::Tag {
+                    indent: "",
+                    tag: "{{ config(materialized='table') }}"
+                },
+                Segment::Tag {
+                    indent: "",
+                    tag: "{{ dbt_utils.union_relations([ref('a'), ref('b')]) }}\n;"
+                },
+                Segment::Tag {
+                    indent: "",
+                    tag: "{# orders #}"
+                },
+                Segment::Tag {
+                    indent: "",
+                    tag: "{% if x %}"
+                },
+                Segment::Sql("\nselect '{%' from {{ t }};"),
+                Segment::Tag {
+                    indent: "  ",
+                    tag: "{% else %}"
+                },
+                Segment::Verbatim("select a {% if y %}, b{% endif %} from t1;"),
+                Segment::Tag {
+                    indent: "",
+                    tag: "{% endif %}"
+                },
+            ]
+        );
+    }
+}
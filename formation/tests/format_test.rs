@@ -5,16 +5,35 @@ use pretty_assertions::assert_eq;
 use rstest::rstest;
 
 use formation::error;
+use formation::options::Style;
 
 const MAX_WIDTH: usize = 100;
 
 fn fixture_paths(name: &str) -> (String, String) {
+    style_fixture_paths("forma", name)
+}
+
+fn style_fixture_paths(style: &str, name: &str) -> (String, String) {
     (
-        format!("tests/sql/{}.sql", name),
-        format!("tests/sql/{}_expected.sql", name),
+        format!("tests/sql/{}/{}.sql", style, name),
+        format!("tests/sql/{}/{}_expected.sql", style, name),
     )
 }
 
+fn assert_style_fixture(style: Style, fixture_paths: (String, String)) -> error::Result<()> {
+    let (input_path, expected_path) = fixture_paths;
+    let sql_string = fs::read_to_string(input_path)?;
+    assert_eq!(
+        String::from_iter(formation::format_with_options(
+            &sql_string,
+            false,
+            &style.options()
+        )?),
+        fs::read_to_string(expected_path)?
+    );
+    Ok(())
+}
+
 #[rstest(
     fixture_paths,
     case::between(fixture_paths("between")),
@@ -35,6 +54,7 @@ fn fixture_paths(name: &str) -> (String, String) {
     case::having(fixture_paths("having")),
     case::inner_join(fixture_paths("inner_join")),
     case::interval(fixture_paths("interval")),
+    case::jinja(fixture_paths("jinja")),
     case::join_using(fixture_paths("join_using")),
     case::listagg(fixture_paths("listagg")),
     case::natural_join(fixture_paths("natural_join")),
@@ -62,3 +82,37 @@ fn test_format(fixture_paths: (String, String)) -> error::Result<()> {
     );
     Ok(())
 }
+
+#[rstest(
+    fixture_paths,
+    case::case(style_fixture_paths("sqlstyle", "case")),
+    case::ctes(style_fixture_paths("sqlstyle", "ctes")),
+    case::group_by(style_fixture_paths("sqlstyle", "group_by")),
+    case::having(style_fixture_paths("sqlstyle", "having")),
+    case::inner_join(style_fixture_paths("sqlstyle", "inner_join")),
+    case::order_by(style_fixture_paths("sqlstyle", "order_by")),
+    case::simple(style_fixture_paths("sqlstyle", "simple")),
+    case::subquery(style_fixture_paths("sqlstyle", "subquery")),
+    case::values(style_fixture_paths("sqlstyle", "values")),
+    case::window_function(style_fixture_paths("sqlstyle", "window_function"))
+)]
+fn test_format_sqlstyle(fixture_paths: (String, String)) -> error::Result<()> {
+    assert_style_fixture(Style::Sqlstyle, fixture_paths)
+}
+
+#[rstest(
+    fixture_paths,
+    case::case(style_fixture_paths("enterprise", "case")),
+    case::ctes(style_fixture_paths("enterprise", "ctes")),
+    case::group_by(style_fixture_paths("enterprise", "group_by")),
+    case::having(style_fixture_paths("enterprise", "having")),
+    case::inner_join(style_fixture_paths("enterprise", "inner_join")),
+    case::order_by(style_fixture_paths("enterprise", "order_by")),
+    case::simple(style_fixture_paths("enterprise", "simple")),
+    case::subquery(style_fixture_paths("enterprise", "subquery")),
+    case::values(style_fixture_paths("enterprise", "values")),
+    case::window_function(style_fixture_paths("enterprise", "window_function"))
+)]
+fn test_format_enterprise(fixture_paths: (String, String)) -> error::Result<()> {
+    assert_style_fixture(Style::Enterprise, fixture_paths)
+}
@@ -0,0 +1,6 @@
+SELECT
+    venuecity, CASE venuecity WHEN 'New York City' THEN 'Big Apple' ELSE 'other' END
+FROM
+    venue
+ORDER BY
+    venueid DESC;
@@ -0,0 +1,10 @@
+SELECT
+    listid, eventid, sum(pricepaid) AS revenue, count(qtysold) AS numtix
+FROM
+    sales
+GROUP BY
+    1, 2
+ORDER BY
+    3, 4, 2, 1
+LIMIT
+    5;
@@ -0,0 +1,6 @@
+SELECT
+    salesid, sellerid, qty, rank() OVER(PARTITION BY sellerid ORDER BY qty DESC) AS rank
+FROM
+    winsales
+ORDER BY
+    2, 3, 1;
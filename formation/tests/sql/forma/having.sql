@@ -0,0 +1,5 @@
+select eventname, sum(pricepaid)
+from sales join event on sales.eventid = event.eventid
+group by 1
+having sum(pricepaid) > 800000
+order by 2 desc, 1;
@@ -0,0 +1,8 @@
+{# Orders placed since the given date. #}
+{% set since = var('since', '2020-01-01') %}
+
+SELECT o.id, o.placed_at, {{ cents_to_dollars('o.amount') }} AS amount
+FROM {{ ref('orders') }} AS o
+WHERE o.placed_at >= '{{ since }}'
+{% if is_incremental() %}
+  {% endif %}
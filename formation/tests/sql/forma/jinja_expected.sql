@@ -0,0 +1,10 @@
+{# Orders placed since the given date. #}
+{% set since = var('since', '2020-01-01') %}
+select
+  o.id, o.placed_at, {{ cents_to_dollars('o.amount') }} as amount
+from
+  {{ ref('orders') }} as o
+where
+  o.placed_at >= '{{ since }}';
+{% if is_incremental() %}
+  {% endif %}
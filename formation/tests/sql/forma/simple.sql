@@ -0,0 +1 @@
+select * from users where created_at > {{date}}
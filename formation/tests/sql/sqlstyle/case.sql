@@ -0,0 +1,6 @@
+select venuecity,
+case venuecity
+when 'New York City'
+then 'Big Apple' else 'other'
+end from venue
+order by venueid desc;
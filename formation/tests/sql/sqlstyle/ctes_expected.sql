@@ -0,0 +1,15 @@
+WITH venue_sales AS (
+    SELECT venuename, venuecity, sum(pricepaid) AS venuename_sales
+      FROM sales, venue, event
+     WHERE venue.venueid = event.venueid AND event.eventid = sales.eventid
+     GROUP BY venuename, venuecity
+  ),
+  top_venues AS (SELECT venuename FROM venue_sales WHERE venuename_sales > 800000)
+
+SELECT venuename, venuecity, venuestate, sum(qtysold) AS venue_qty, sum(pricepaid) AS venue_sales
+  FROM sales, venue, event
+ WHERE venue.venueid = event.venueid
+   AND event.eventid = sales.eventid
+   AND venuename IN (SELECT venuename FROM top_venues)
+ GROUP BY venuename, venuecity, venuestate
+ ORDER BY venuename;
@@ -0,0 +1,5 @@
+SELECT eventname, sum(pricepaid)
+  FROM sales JOIN event ON sales.eventid = event.eventid
+ GROUP BY 1
+HAVING sum(pricepaid) > 800000
+ ORDER BY 2 DESC, 1;
@@ -0,0 +1,15 @@
+select catgroup1, sold, unsold
+from
+(select catgroup, sum(qtysold) as sold
+from category c, event e, sales s
+where c.catid = e.catid and e.eventid = s.eventid
+group by catgroup) as a(catgroup1, sold)
+join
+(select catgroup, sum(numtickets)-sum(qtysold) as unsold
+from category c, event e, sales s, listing l
+where c.catid = e.catid and e.eventid = s.eventid
+and s.listid = l.listid
+group by catgroup) as b(catgroup2, unsold)
+
+on a.catgroup1 = b.catgroup2
+order by 1;
@@ -0,0 +1,14 @@
+SELECT catgroup1, sold, unsold
+  FROM (
+         SELECT catgroup, sum(qtysold) AS sold
+           FROM category AS c, event AS e, sales AS s
+          WHERE c.catid = e.catid AND e.eventid = s.eventid
+          GROUP BY catgroup
+       ) AS a (catgroup1, sold)
+       JOIN (
+         SELECT catgroup, sum(numtickets) - sum(qtysold) AS unsold
+           FROM category AS c, event AS e, sales AS s, listing AS l
+          WHERE c.catid = e.catid AND e.eventid = s.eventid AND s.listid = l.listid
+          GROUP BY catgroup
+       ) AS b (catgroup2, unsold) ON a.catgroup1 = b.catgroup2
+ ORDER BY 1;
@@ -0,0 +1,2 @@
+select * from t1 order by created_at asc nulls last;
+select * from t1 order by created_at desc nulls first;
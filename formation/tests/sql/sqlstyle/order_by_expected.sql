@@ -0,0 +1,2 @@
+SELECT * FROM t1 ORDER BY created_at ASC NULLS LAST;
+SELECT * FROM t1 ORDER BY created_at DESC NULLS FIRST;
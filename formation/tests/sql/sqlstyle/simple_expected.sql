@@ -0,0 +1 @@
+SELECT * FROM users WHERE created_at > {{date}};
@@ -0,0 +1,11 @@
+SELECT qtr,
+       sum(pricepaid) AS qtrsales,
+       (
+         SELECT sum(pricepaid)
+           FROM sales JOIN _date ON sales.dateid = _date.dateid
+          WHERE qtr = '1' AND year = 2008
+       ) AS q1sales
+  FROM sales JOIN _date ON sales.dateid = _date.dateid
+ WHERE qtr IN ('2', '3') AND year = 2008
+ GROUP BY qtr
+ ORDER BY qtr;
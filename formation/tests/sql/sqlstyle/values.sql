@@ -0,0 +1 @@
+values ('foo', 'bar','baz')
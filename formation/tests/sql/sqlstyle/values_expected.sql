@@ -0,0 +1 @@
+VALUES ('foo', 'bar', 'baz');